@@ -0,0 +1,549 @@
+/// Programmatic configuration of a genetic algorithm
+use super::cellular::{CellularUpdate, Neighbourhood};
+use super::individual::{Genotype, MultiObjective};
+use super::island::{IslandModel, MigrationPolicy, MigrationTopology};
+use super::moead::{Aggregation, MoeaD};
+use super::observer::Observer;
+use super::pareto::Nsga2;
+use super::population::{
+    create_rng, LocalSearch, ParentSelection, PopulationModel, StandardPopulation,
+    SurvivorSelection,
+};
+use super::spea2::Spea2;
+use super::termination::Termination;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Settings of a genetic algorithm, independent of how they were provided
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    /// Population size
+    pub population: usize,
+    /// Maximum number of generations, 0 means run until reaching the target fitness
+    pub max_generations: u32,
+    /// Target fitness
+    pub target_fitness: f64,
+    /// Wall clock time after which the evolution stops
+    pub time_limit: Option<Duration>,
+    /// Number of fitness evaluations after which the evolution stops
+    pub max_evaluations: Option<u64>,
+    /// Number of generations without improvement after which the evolution stops
+    pub stagnation: Option<u32>,
+    /// Population diversity below which the evolution stops
+    pub min_diversity: Option<f64>,
+    /// Additional stop condition, combined with the ones above
+    pub termination: Option<Termination>,
+    /// Probability of mutating each offspring
+    pub mutation_rate: f64,
+    /// Probability of producing an offspring through crossover instead of copying a parent
+    pub crossover_rate: f64,
+    /// Whether or not the best individual always survives, by default on in every population
+    /// model except (μ,λ)
+    pub elitism: Option<bool>,
+    /// Whether or not to minimize the fitness function
+    pub minimize: bool,
+    /// Parent selection strategy
+    pub parent_selection: ParentSelection,
+    /// Number of individuals competing in each tournament
+    pub tournament_size: usize,
+    /// Selection pressure between 1.0 and 2.0 for rank selection
+    pub selection_pressure: f64,
+    /// Survivor selection strategy
+    pub survivor_selection: SurvivorSelection,
+    /// Population model
+    pub population_model: PopulationModel,
+    /// Number of offspring replacing existing individuals each steady state step
+    pub replacement_count: usize,
+    /// Number of offspring (λ) in the (μ+λ) and (μ,λ) models, defaults to the population size
+    pub offspring: Option<usize>,
+    /// Local search applied to offspring before evaluation
+    pub local_search: LocalSearch,
+    /// Seed for the random number generator
+    pub seed: Option<u64>,
+    /// Width of the grid of a cellular population, 0 picks the most square grid
+    pub grid_width: usize,
+    /// Shape of the neighbourhood of a cell in a cellular population
+    pub neighbourhood: Neighbourhood,
+    /// Distance from a cell to the edge of its neighbourhood
+    pub neighbourhood_radius: usize,
+    /// Order in which the cells of a cellular population are replaced
+    pub cellular_update: CellularUpdate,
+    /// Number of populations evolving side by side in the island model
+    pub islands: usize,
+    /// Number of generations between migrations in the island model
+    pub migration_interval: u32,
+    /// Number of individuals each island sends per migration
+    pub migrants: usize,
+    /// Which islands send migrants to which
+    pub migration_topology: MigrationTopology,
+    /// Which individuals leave an island
+    pub migration_policy: MigrationPolicy,
+    /// Threads evaluating fitness with the parallel feature, 0 means one per CPU core
+    pub threads: usize,
+    /// Size of the archive of non-dominated individuals in SPEA2, defaults to the population
+    /// size
+    pub archive_size: Option<usize>,
+    /// How MOEA/D scalarizes the objectives of each subproblem
+    pub aggregation: Aggregation,
+    /// Penalty θ on the distance from the weight vector in PBI aggregation
+    pub pbi_penalty: f64,
+    /// Number of closest weight vectors whose subproblems share offspring in MOEA/D
+    pub weight_neighbours: usize,
+    /// Point the hypervolume of a Pareto front is measured from, defaults to the nadir point of
+    /// the final front moved away by 10% of its range
+    pub reference_point: Option<Vec<f64>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            population: 50,
+            max_generations: 0,
+            target_fitness: 1.0,
+            time_limit: None,
+            max_evaluations: None,
+            stagnation: None,
+            min_diversity: None,
+            termination: None,
+            mutation_rate: 0.1,
+            crossover_rate: 0.5,
+            elitism: None,
+            minimize: false,
+            parent_selection: ParentSelection::RouletteWheel,
+            tournament_size: 3,
+            selection_pressure: 1.5,
+            survivor_selection: SurvivorSelection::FitnessBased,
+            population_model: PopulationModel::Generational,
+            replacement_count: 2,
+            offspring: None,
+            local_search: LocalSearch::None,
+            seed: None,
+            grid_width: 0,
+            neighbourhood: Neighbourhood::VonNeumann,
+            neighbourhood_radius: 1,
+            cellular_update: CellularUpdate::Synchronous,
+            islands: 1,
+            migration_interval: 20,
+            migrants: 2,
+            migration_topology: MigrationTopology::Ring,
+            migration_policy: MigrationPolicy::Best,
+            threads: 0,
+            archive_size: None,
+            aggregation: Aggregation::Tchebycheff,
+            pbi_penalty: 5.0,
+            weight_neighbours: 20,
+            reference_point: None,
+        }
+    }
+}
+
+/// A setting that is out of range
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError(pub String);
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid configuration: {}", self.0)
+    }
+}
+
+impl Config {
+    /// Check that all settings are in range
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError(message));
+
+        if self.population == 0 {
+            return invalid("population must hold at least one individual".to_string());
+        }
+
+        for (name, rate) in &[
+            ("mutation rate", self.mutation_rate),
+            ("crossover rate", self.crossover_rate),
+        ] {
+            if !(0.0..=1.0).contains(rate) {
+                return invalid(format!("{} {} is not between 0 and 1", name, rate));
+            }
+        }
+
+        if !(1.0..=2.0).contains(&self.selection_pressure) {
+            return invalid(format!(
+                "selection pressure {} is not between 1 and 2",
+                self.selection_pressure
+            ));
+        }
+
+        // A cell only competes with its own offspring, so there is no tournament to hold
+        if matches!(self.population_model, PopulationModel::Cellular)
+            && matches!(self.survivor_selection, SurvivorSelection::TournamentBased)
+        {
+            return invalid(
+                "tournament based survivor selection needs a non-cellular population model"
+                    .to_string(),
+            );
+        }
+
+        if matches!(self.population_model, PopulationModel::MuCommaLambda)
+            && self.offspring.unwrap_or(self.population) < self.population
+        {
+            return invalid(format!(
+                "(μ,λ) needs at least as many offspring as the population of {}",
+                self.population
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether or not the best individual always survives
+    pub fn elitism(&self) -> bool {
+        self.elitism.unwrap_or(!matches!(
+            self.population_model,
+            PopulationModel::MuCommaLambda
+        ))
+    }
+
+    /// The evolution stops as soon as any of the configured conditions is met
+    pub fn termination(&self) -> Termination {
+        let mut conditions = vec![Termination::TargetFitness(self.target_fitness)];
+
+        // Max generations of 0 means run until another condition is met
+        if self.max_generations > 0 {
+            conditions.push(Termination::MaxGenerations(self.max_generations));
+        }
+        if let Some(limit) = self.time_limit {
+            conditions.push(Termination::TimeLimit(limit));
+        }
+        if let Some(evaluations) = self.max_evaluations {
+            conditions.push(Termination::MaxEvaluations(evaluations));
+        }
+        if let Some(generations) = self.stagnation {
+            conditions.push(Termination::Stagnation(generations));
+        }
+        if let Some(threshold) = self.min_diversity {
+            conditions.push(Termination::Diversity(threshold));
+        }
+        if let Some(termination) = &self.termination {
+            conditions.push(termination.clone());
+        }
+
+        Termination::AnyOf(conditions)
+    }
+}
+
+/// The genetic algorithm engine, created through `GeneticAlgorithm::builder()`
+pub type GeneticAlgorithm<T> = StandardPopulation<T>;
+
+/// Builder for setting up a genetic algorithm without going through the command line
+pub struct GeneticAlgorithmBuilder<T>
+where
+    T: Genotype + PartialOrd,
+{
+    config: Config,
+    observers: Vec<Box<dyn Observer<T>>>,
+    genotype: PhantomData<T>,
+}
+
+impl<T> StandardPopulation<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    /// Start building a new genetic algorithm with default settings
+    pub fn builder() -> GeneticAlgorithmBuilder<T> {
+        GeneticAlgorithmBuilder::new(Config::default())
+    }
+}
+
+impl<T> GeneticAlgorithmBuilder<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    /// Start building a new genetic algorithm from existing settings
+    pub fn new(config: Config) -> Self {
+        GeneticAlgorithmBuilder {
+            config,
+            observers: Vec::new(),
+            genotype: PhantomData,
+        }
+    }
+
+    /// Number of individuals in the population, at least 1
+    pub fn population(mut self, population: usize) -> Self {
+        self.config.population = population;
+        self
+    }
+
+    /// Stop after this many generations, 0 runs until the target fitness is reached
+    pub fn max_generations(mut self, max_generations: u32) -> Self {
+        self.config.max_generations = max_generations;
+        self
+    }
+
+    /// Stop once the best individual reaches this fitness
+    pub fn target_fitness(mut self, target_fitness: f64) -> Self {
+        self.config.target_fitness = target_fitness;
+        self
+    }
+
+    /// Stop once this much wall clock time has passed
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.config.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Stop after this many fitness evaluations
+    pub fn max_evaluations(mut self, max_evaluations: u64) -> Self {
+        self.config.max_evaluations = Some(max_evaluations);
+        self
+    }
+
+    /// Stop after this many generations without improvement
+    pub fn stagnation(mut self, generations: u32) -> Self {
+        self.config.stagnation = Some(generations);
+        self
+    }
+
+    /// Stop once the fraction of distinct genotypes drops below this
+    pub fn min_diversity(mut self, min_diversity: f64) -> Self {
+        self.config.min_diversity = Some(min_diversity);
+        self
+    }
+
+    /// Also stop when the given condition is met
+    pub fn termination(mut self, termination: Termination) -> Self {
+        self.config.termination = Some(termination);
+        self
+    }
+
+    /// Probability between 0 and 1 of mutating each offspring
+    pub fn mutation_rate(mut self, mutation_rate: f64) -> Self {
+        self.config.mutation_rate = mutation_rate;
+        self
+    }
+
+    /// Probability between 0 and 1 of breeding an offspring by crossover
+    pub fn crossover_rate(mut self, crossover_rate: f64) -> Self {
+        self.config.crossover_rate = crossover_rate;
+        self
+    }
+
+    /// Whether or not the best individual always survives
+    pub fn elitism(mut self, elitism: bool) -> Self {
+        self.config.elitism = Some(elitism);
+        self
+    }
+
+    /// Whether or not lower fitness is better
+    pub fn minimize(mut self, minimize: bool) -> Self {
+        self.config.minimize = minimize;
+        self
+    }
+
+    /// How parents are chosen for breeding
+    pub fn parent_selection(mut self, parent_selection: ParentSelection) -> Self {
+        self.config.parent_selection = parent_selection;
+        self
+    }
+
+    /// Number of individuals competing in each tournament
+    pub fn tournament_size(mut self, tournament_size: usize) -> Self {
+        self.config.tournament_size = tournament_size;
+        self
+    }
+
+    /// Selection pressure of rank selection, between 1 and 2
+    pub fn selection_pressure(mut self, selection_pressure: f64) -> Self {
+        self.config.selection_pressure = selection_pressure;
+        self
+    }
+
+    /// How individuals are chosen for replacement
+    pub fn survivor_selection(mut self, survivor_selection: SurvivorSelection) -> Self {
+        self.config.survivor_selection = survivor_selection;
+        self
+    }
+
+    /// How offspring replace the population each generation
+    pub fn population_model(mut self, population_model: PopulationModel) -> Self {
+        self.config.population_model = population_model;
+        self
+    }
+
+    /// Number of offspring replacing individuals each steady state step
+    pub fn replacement_count(mut self, replacement_count: usize) -> Self {
+        self.config.replacement_count = replacement_count;
+        self
+    }
+
+    /// Number of offspring (λ) in the (μ+λ) and (μ,λ) models
+    pub fn offspring(mut self, offspring: usize) -> Self {
+        self.config.offspring = Some(offspring);
+        self
+    }
+
+    /// Local search applied to every offspring before evaluation
+    pub fn local_search(mut self, local_search: LocalSearch) -> Self {
+        self.config.local_search = local_search;
+        self
+    }
+
+    /// Seed the random number generator to make runs repeatable
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Width of the grid of a cellular population, 0 picks the most square grid
+    pub fn grid_width(mut self, grid_width: usize) -> Self {
+        self.config.grid_width = grid_width;
+        self
+    }
+
+    /// Shape of the neighbourhood of a cell
+    pub fn neighbourhood(mut self, neighbourhood: Neighbourhood) -> Self {
+        self.config.neighbourhood = neighbourhood;
+        self
+    }
+
+    /// Distance from a cell to the edge of its neighbourhood
+    pub fn neighbourhood_radius(mut self, neighbourhood_radius: usize) -> Self {
+        self.config.neighbourhood_radius = neighbourhood_radius;
+        self
+    }
+
+    /// Order in which the cells are replaced
+    pub fn cellular_update(mut self, cellular_update: CellularUpdate) -> Self {
+        self.config.cellular_update = cellular_update;
+        self
+    }
+
+    /// Number of populations evolving side by side
+    pub fn islands(mut self, islands: usize) -> Self {
+        self.config.islands = islands;
+        self
+    }
+
+    /// Number of generations between migrations
+    pub fn migration_interval(mut self, migration_interval: u32) -> Self {
+        self.config.migration_interval = migration_interval;
+        self
+    }
+
+    /// Number of individuals each island sends per migration
+    pub fn migrants(mut self, migrants: usize) -> Self {
+        self.config.migrants = migrants;
+        self
+    }
+
+    /// Which islands send migrants to which
+    pub fn migration_topology(mut self, migration_topology: MigrationTopology) -> Self {
+        self.config.migration_topology = migration_topology;
+        self
+    }
+
+    /// Which individuals leave an island
+    pub fn migration_policy(mut self, migration_policy: MigrationPolicy) -> Self {
+        self.config.migration_policy = migration_policy;
+        self
+    }
+
+    /// Threads evaluating fitness with the parallel feature, 0 means one per CPU core
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = threads;
+        self
+    }
+
+    /// Size of the SPEA2 archive
+    pub fn archive_size(mut self, archive_size: usize) -> Self {
+        self.config.archive_size = Some(archive_size);
+        self
+    }
+
+    /// How MOEA/D scalarizes the objectives of each subproblem
+    pub fn aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.config.aggregation = aggregation;
+        self
+    }
+
+    /// Penalty on the distance from the weight vector in PBI aggregation
+    pub fn pbi_penalty(mut self, pbi_penalty: f64) -> Self {
+        self.config.pbi_penalty = pbi_penalty;
+        self
+    }
+
+    /// Number of neighbouring subproblems sharing offspring in MOEA/D
+    pub fn weight_neighbours(mut self, weight_neighbours: usize) -> Self {
+        self.config.weight_neighbours = weight_neighbours;
+        self
+    }
+
+    /// Point the hypervolume of the Pareto front is measured from
+    pub fn reference_point(mut self, reference_point: Vec<f64>) -> Self {
+        self.config.reference_point = Some(reference_point);
+        self
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub fn observer(mut self, observer: impl Observer<T> + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Create the initial population for the problem described by the given context, or an
+    /// error if any setting is out of range
+    pub fn try_build(self, context: T::Context) -> Result<GeneticAlgorithm<T>, ConfigError> {
+        self.config.validate()?;
+
+        let rng = create_rng(self.config.seed);
+        let mut population = StandardPopulation::with_rng(self.config, context, rng);
+
+        for observer in self.observers {
+            population.add_observer(observer);
+        }
+
+        Ok(population)
+    }
+
+    /// Create the initial population for the problem described by the given context. Panics if
+    /// any setting is out of range.
+    pub fn build(self, context: T::Context) -> GeneticAlgorithm<T> {
+        match self.try_build(context) {
+            Ok(population) => population,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create an island model of `islands` populations for the problem described by the
+    /// given context
+    pub fn build_islands(self, context: T::Context) -> IslandModel<T> {
+        IslandModel::from(self.build(context))
+    }
+
+    /// Create an NSGA-II population for the multi-objective problem described by the given
+    /// context
+    pub fn build_nsga2(self, context: T::Context) -> Nsga2<T>
+    where
+        T::Phenotype: MultiObjective<T::Context>,
+    {
+        Nsga2::from(self.build(context))
+    }
+
+    /// Create a SPEA2 population for the multi-objective problem described by the given context
+    pub fn build_spea2(self, context: T::Context) -> Spea2<T>
+    where
+        T::Phenotype: MultiObjective<T::Context>,
+    {
+        Spea2::from(self.build(context))
+    }
+
+    /// Create a MOEA/D population for the multi-objective problem described by the given
+    /// context, with one subproblem per individual
+    pub fn build_moead(self, context: T::Context) -> MoeaD<T>
+    where
+        T::Phenotype: MultiObjective<T::Context>,
+    {
+        MoeaD::from(self.build(context))
+    }
+}
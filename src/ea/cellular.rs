@@ -0,0 +1,85 @@
+/// Grid layout of the cellular population model
+use serde::{Deserialize, Serialize};
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Shape of the neighbourhood of a cell
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    pub enum Neighbourhood {
+        VonNeumann,
+        Moore,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Order in which the cells of the grid are replaced
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    pub enum CellularUpdate {
+        Synchronous,
+        Asynchronous,
+    }
+}
+
+/// Width of the grid for the given number of cells, 0 picks the most square grid
+pub(crate) fn grid_width(cells: usize, width: usize) -> usize {
+    if width > 0 {
+        width.min(cells)
+    } else {
+        ((cells as f64).sqrt().round() as usize).max(1)
+    }
+}
+
+/// Indices of the cells in the neighbourhood of every cell on a toroidal grid, including the
+/// cell itself. The last row of the grid may be incomplete.
+pub(crate) fn neighbourhoods(
+    cells: usize,
+    width: usize,
+    shape: Neighbourhood,
+    radius: usize,
+) -> Vec<Vec<usize>> {
+    let width = grid_width(cells, width);
+    let height = cells.div_ceil(width);
+    let radius = radius as isize;
+
+    (0..cells)
+        .map(|cell| {
+            let (x, y) = ((cell % width) as isize, (cell / width) as isize);
+            let mut neighbours = Vec::new();
+
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let inside = match shape {
+                        Neighbourhood::VonNeumann => dx.abs() + dy.abs() <= radius,
+                        Neighbourhood::Moore => true,
+                    };
+                    let nx = (x + dx).rem_euclid(width as isize) as usize;
+                    let ny = (y + dy).rem_euclid(height as isize) as usize;
+                    let neighbour = ny * width + nx;
+
+                    if inside && neighbour < cells {
+                        neighbours.push(neighbour);
+                    }
+                }
+            }
+
+            // Small grids wrap around onto the same cells
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            neighbours
+        })
+        .collect()
+}
+
+#[test]
+fn test_neighbourhoods() {
+    let von_neumann = neighbourhoods(25, 5, Neighbourhood::VonNeumann, 1);
+    let moore = neighbourhoods(25, 0, Neighbourhood::Moore, 1);
+    let wide = neighbourhoods(100, 10, Neighbourhood::VonNeumann, 2);
+
+    assert_eq!(von_neumann[0], vec![0, 1, 4, 5, 20]);
+    assert_eq!(von_neumann[12], vec![7, 11, 12, 13, 17]);
+    assert_eq!(moore[6], vec![0, 1, 2, 5, 6, 7, 10, 11, 12]);
+    assert!(wide.iter().all(|n| n.len() == 13));
+}
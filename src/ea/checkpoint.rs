@@ -0,0 +1,61 @@
+/// Saving and restoring the state of an evolution
+use super::builder::Config;
+use super::individual::{Genotype, Individual};
+use super::population::{EvolutionRng, EvolutionStats};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Everything needed to continue an evolution exactly where it stopped
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: Genotype + PartialOrd")]
+pub(crate) struct Checkpoint<T>
+where
+    T: Genotype + PartialOrd,
+{
+    pub config: Config,
+    pub rng: EvolutionRng,
+    pub population: Vec<Individual<T>>,
+    pub stats: EvolutionStats,
+    /// Time spent evolving before the checkpoint was taken
+    pub elapsed: Duration,
+}
+
+/// Only the settings of a checkpoint, for setting up the problem before resuming
+#[derive(Deserialize)]
+struct CheckpointConfig {
+    config: Config,
+}
+
+impl<T> Checkpoint<T>
+where
+    T: Genotype + PartialOrd,
+{
+    /// Write the checkpoint to a temporary file first, so an interrupted write never
+    /// destroys the previous checkpoint
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temporary = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&temporary, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+/// Read the settings stored in a checkpoint file
+pub fn checkpoint_config(path: &Path) -> io::Result<Config> {
+    let reader = BufReader::new(File::open(path)?);
+    let checkpoint: CheckpointConfig = serde_json::from_reader(reader)?;
+
+    Ok(checkpoint.config)
+}
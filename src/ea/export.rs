@@ -0,0 +1,224 @@
+/// Export of per generation statistics to files
+use super::builder::Config;
+use super::individual::{Genotype, Individual, MaybeSend};
+use super::observer::{Control, Observer};
+use super::population::EvolutionStats;
+use super::report::RunResult;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available statistics file formats
+    #[derive(Copy, Clone, Debug)]
+    pub enum StatsFormat {
+        Csv,
+        Jsonl,
+    }
+}
+
+/// Columns written for every generation
+const COLUMNS: [&str; 11] = [
+    "generation",
+    "elapsed",
+    "best",
+    "mean",
+    "median",
+    "worst",
+    "std_dev",
+    "mutations",
+    "crossovers",
+    "evaluations",
+    "diversity",
+];
+
+/// JSON has no representation of NaN or infinity
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Observer writing the statistics of every generation to a CSV or JSON Lines file
+pub struct StatsWriter<W: Write> {
+    writer: W,
+    format: StatsFormat,
+    /// Whether the CSV header still has to be written
+    header: bool,
+    failed: bool,
+}
+
+impl StatsWriter<BufWriter<File>> {
+    /// Create a new statistics file, overwriting any existing file
+    pub fn create(path: &Path, format: StatsFormat) -> io::Result<Self> {
+        Ok(StatsWriter::new(
+            BufWriter::new(File::create(path)?),
+            format,
+        ))
+    }
+
+    /// Continue an existing statistics file, as when resuming from a checkpoint, or create it
+    /// if it does not exist
+    pub fn append(path: &Path, format: StatsFormat) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut writer = StatsWriter::new(BufWriter::new(file), format);
+        writer.header = empty;
+
+        Ok(writer)
+    }
+}
+
+impl<W: Write> StatsWriter<W> {
+    /// Write statistics to the given writer
+    pub fn new(writer: W, format: StatsFormat) -> Self {
+        StatsWriter {
+            writer,
+            format,
+            header: true,
+            failed: false,
+        }
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        match self.format {
+            StatsFormat::Csv if self.header => writeln!(self.writer, "{}", COLUMNS.join(",")),
+            _ => Ok(()),
+        }
+    }
+
+    fn write_stats(&mut self, stats: &EvolutionStats) -> io::Result<()> {
+        let values = [
+            stats.generation as f64,
+            stats.elapsed as f64,
+            stats.fitness,
+            stats.mean,
+            stats.median,
+            stats.worst,
+            stats.std_dev,
+            stats.mutations as f64,
+            stats.crossovers as f64,
+            stats.evaluations as f64,
+            stats.diversity,
+        ];
+
+        match self.format {
+            StatsFormat::Csv => {
+                let row: Vec<String> = values.iter().map(f64::to_string).collect();
+                writeln!(self.writer, "{}", row.join(","))
+            }
+            StatsFormat::Jsonl => {
+                let fields: Vec<String> = COLUMNS
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(column, value)| format!("\"{}\":{}", column, json_number(*value)))
+                    .collect();
+                writeln!(self.writer, "{{{}}}", fields.join(","))
+            }
+        }
+    }
+
+    /// Stop writing after the first error instead of reporting it every generation
+    fn check(&mut self, result: io::Result<()>) {
+        if let Err(e) = result {
+            eprintln!("Failed to write statistics: {}", e);
+            self.failed = true;
+        }
+    }
+}
+
+impl<T, W> Observer<T> for StatsWriter<W>
+where
+    T: Genotype + PartialOrd,
+    W: Write + MaybeSend,
+{
+    fn on_start(&mut self, _config: &Config, _population: &[Individual<T>]) {
+        let result = self.write_header();
+        self.check(result);
+    }
+
+    fn needs_diversity(&self) -> bool {
+        true
+    }
+
+    fn on_generation(&mut self, stats: &EvolutionStats, _population: &[Individual<T>]) -> Control {
+        if !self.failed {
+            let result = self.write_stats(stats);
+            self.check(result);
+        }
+
+        Control::Continue
+    }
+
+    fn on_finish(&mut self, _result: &RunResult<T>) {
+        if !self.failed {
+            let result = self.writer.flush();
+            self.check(result);
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_exported_stats() {
+    use super::builder::GeneticAlgorithm;
+    use super::population::Population;
+    use crate::problems::NQueens;
+
+    let buffer = SharedBuffer::default();
+    let result = GeneticAlgorithm::<NQueens>::builder()
+        .seed(7)
+        .max_generations(5)
+        .target_fitness(2.0)
+        .observer(StatsWriter::new(buffer.clone(), StatsFormat::Csv))
+        .build(12)
+        .evolve();
+
+    let csv = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], COLUMNS.join(","));
+    assert_eq!(lines.len(), 6);
+
+    let last: Vec<f64> = lines[5].split(',').map(|v| v.parse().unwrap()).collect();
+    assert_eq!(last[0], 5.0);
+    assert_eq!(last[2], result.stats.fitness);
+    assert_eq!(last[9], result.evaluations as f64);
+    // Diversity is computed for the writer even without a diversity termination
+    assert!(last[10] > 0.0 && last[10] <= 1.0);
+
+    // Resuming appends rows without repeating the header
+    let path = std::env::temp_dir().join(format!("rust-ga-stats-{}.csv", std::process::id()));
+    for _ in 0..2 {
+        let writer = StatsWriter::append(&path, StatsFormat::Csv).unwrap();
+        GeneticAlgorithm::<NQueens>::builder()
+            .seed(7)
+            .max_generations(5)
+            .target_fitness(2.0)
+            .observer(writer)
+            .build(12)
+            .evolve();
+    }
+    let appended = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(appended.lines().count(), 11);
+    assert_eq!(appended.matches("generation").count(), 1);
+}
@@ -1,65 +1,187 @@
 use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Display;
 
-use crate::Options;
+/// Send + Sync when fitness is evaluated in parallel, no requirement otherwise
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> MaybeSync for T {}
+
+/// Send + Sync when fitness is evaluated in parallel, no requirement otherwise
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSync for T {}
+
+/// Send when populations evolve on several threads, no requirement otherwise
+#[cfg(feature = "parallel")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "parallel")]
+impl<T: Send> MaybeSend for T {}
+
+/// Send when populations evolve on several threads, no requirement otherwise
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSend for T {}
+
+/// What a genotype decodes to for evaluation, like a board or a program
+pub trait Phenotype<C>: Clone + fmt::Debug + MaybeSync {
+    /// Evaluate the fitness of this Phenotype
+    fn fitness(&self, context: &C) -> f64;
+}
+
+/// A phenotype with several objectives, which are all minimized or all maximized like the
+/// fitness
+pub trait MultiObjective<C>: Phenotype<C> {
+    /// Evaluate every objective of this Phenotype
+    fn objectives(&self, context: &C) -> Vec<f64>;
+}
 
-/// TODO: Possibly add Phenotype as associated type and do some Into/From trait magic in Population bounds
 /// TODO: Make this into a struct generic over T where T has bounds without Self
-pub trait Genotype {
+pub trait Genotype: Clone + Serialize + DeserializeOwned + MaybeSync {
+    /// Problem instance data shared by all genotypes in a population, like a distance matrix
+    type Context: MaybeSync;
+    /// What this genotype decodes to, which is what the fitness is computed on. Genotypes
+    /// that are evaluated directly decode to a copy of themselves.
+    type Phenotype: Phenotype<Self::Context>;
+
     /// Create a new Genotype
-    fn new(rng: &mut impl Rng, options: &Options) -> Self;
+    fn new(rng: &mut impl Rng, context: &Self::Context) -> Self;
+    /// Decode this genotype into its phenotype
+    fn decode(&self, context: &Self::Context) -> Self::Phenotype;
     /// Mutate this genotype
-    fn mutate(&mut self, rng: &mut impl Rng);
+    fn mutate(&mut self, context: &Self::Context, rng: &mut impl Rng);
     /// Perform crossover and produce a new offspring
-    fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Self;
-}
-
-/// Putting fitness into different Phenotype trait for future separation of decode
-pub trait Phenotype {
-    /// Evaluate the fitness of this Phenotype
-    fn fitness(&self) -> f64;
+    fn crossover(&self, other: &Self, context: &Self::Context, rng: &mut impl Rng) -> Self;
+    /// Improve this genotype with a local search, if the problem has one
+    fn improve(&self, _context: &Self::Context) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+    /// Size of this genotype, which decides between individuals of equal fitness in favour of
+    /// the smaller one. Only genotypes that can grow, like expression trees, need a size.
+    fn size(&self) -> usize {
+        0
+    }
 }
 
-/// Individual wraps the T: Genotype + Phenotype with additional metadata
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+/// Individual wraps the T: Genotype with additional metadata
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "T: Genotype + PartialOrd")]
 pub struct Individual<T>
 where
-    T: Genotype + Phenotype + PartialOrd,
+    T: Genotype + PartialOrd,
 {
     pub fitness: f64,
     pub generation: i32,
     pub genotype: T,
+    /// The decoded genotype, cached when the individual is evaluated
+    #[serde(skip)]
+    pub phenotype: Option<T::Phenotype>,
+    /// Values of the objectives in multi-objective optimisation, empty otherwise
+    #[serde(default)]
+    pub objectives: Vec<f64>,
 }
 
-/// Convenience method to evaluate the fitness of a genotype
-impl<T> Individual<T>
+/// Individuals compare by fitness, generation and genotype, the phenotype follows from the
+/// genotype
+impl<T> PartialEq for Individual<T>
 where
-    T: Genotype + Phenotype + PartialOrd,
+    T: Genotype + PartialOrd,
 {
-    pub fn evaluate(&mut self) {
-        self.fitness = self.genotype.fitness();
+    fn eq(&self, other: &Self) -> bool {
+        (self.fitness, self.generation, &self.genotype)
+            == (other.fitness, other.generation, &other.genotype)
     }
 }
 
-/// Convenience method to perform crossover on the underlying genotype
+impl<T> PartialOrd for Individual<T>
+where
+    T: Genotype + PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.fitness, self.generation, &self.genotype).partial_cmp(&(
+            other.fitness,
+            other.generation,
+            &other.genotype,
+        ))
+    }
+}
+
+/// Convenience methods to decode and evaluate the genotype
 impl<T> Individual<T>
 where
-    T: Genotype + Phenotype + PartialOrd,
+    T: Genotype + PartialOrd,
 {
-    pub fn crossover(&self, other: &Self, generation: i32, rng: &mut impl Rng) -> Self {
+    /// An individual that has not been evaluated yet
+    pub fn new(genotype: T, generation: i32) -> Self {
         Individual {
-            generation,
             fitness: 0.0,
-            genotype: self.genotype.crossover(&other.genotype, rng),
+            generation,
+            genotype,
+            phenotype: None,
+            objectives: Vec::new(),
+        }
+    }
+
+    /// Decode the genotype, caching the phenotype
+    pub fn decode(&mut self, context: &T::Context) -> &T::Phenotype {
+        self.phenotype.insert(self.genotype.decode(context))
+    }
+
+    /// Decode the genotype and compute the fitness of the phenotype
+    pub fn evaluate(&mut self, context: &T::Context) {
+        self.fitness = self.decode(context).fitness(context);
+    }
+}
+
+/// Convenience method to evaluate every objective of a multi-objective phenotype
+impl<T> Individual<T>
+where
+    T: Genotype + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    /// Compute the objectives of the phenotype, decoding it first if needed
+    pub fn evaluate_objectives(&mut self, context: &T::Context) {
+        if self.phenotype.is_none() {
+            self.decode(context);
+        }
+        if let Some(phenotype) = &self.phenotype {
+            self.objectives = phenotype.objectives(context);
         }
     }
 }
 
+/// Convenience method to perform crossover on the underlying genotype
+impl<T> Individual<T>
+where
+    T: Genotype + PartialOrd,
+{
+    pub fn crossover(
+        &self,
+        other: &Self,
+        generation: i32,
+        context: &T::Context,
+        rng: &mut impl Rng,
+    ) -> Self {
+        Individual::new(
+            self.genotype.crossover(&other.genotype, context, rng),
+            generation,
+        )
+    }
+}
+
 /// String representation of an indididual
 impl<T> Display for Individual<T>
 where
-    T: Genotype + Phenotype + Display + PartialOrd,
+    T: Genotype + Display + PartialOrd,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -0,0 +1,350 @@
+/// Island model evolving several populations that exchange individuals
+use super::builder::Config;
+use super::individual::{Genotype, Individual};
+use super::observer::{Control, Observer};
+use super::population::{
+    create_rng, notify_observers, sort, tracks_diversity, update_fitness_stats, EvolutionRng,
+    EvolutionStats, Population, StandardPopulation,
+};
+use super::report::{RunResult, TerminationReason};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::time::SystemTime;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Which islands send migrants to which
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    pub enum MigrationTopology {
+        Ring,
+        FullyConnected,
+        Random,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Which individuals leave an island
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    pub enum MigrationPolicy {
+        Best,
+        Random,
+    }
+}
+
+/// Several populations evolving independently, exchanging migrants every migration interval.
+/// Observers and termination criteria see the islands as one combined population after every
+/// generation.
+pub struct IslandModel<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    config: Config,
+    islands: Vec<StandardPopulation<T>>,
+    rng: EvolutionRng,
+    stats: EvolutionStats,
+    /// All individuals of all islands sorted with the best first, as of the last generation
+    population: Vec<Individual<T>>,
+    started: SystemTime,
+    observers: Vec<Box<dyn Observer<T>>>,
+}
+
+/// Turn a population into the first of `config.islands` islands, taking over its observers
+impl<T> From<StandardPopulation<T>> for IslandModel<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    fn from(population: StandardPopulation<T>) -> Self {
+        let config = population.config().clone();
+        let (islands, rng, observers) = population.into_islands(config.islands.max(1));
+
+        IslandModel {
+            stats: EvolutionStats {
+                max_generations: config.max_generations,
+                ..Default::default()
+            },
+            config,
+            islands,
+            rng,
+            population: Vec::new(),
+            started: SystemTime::now(),
+            observers,
+        }
+    }
+}
+
+impl<T> IslandModel<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    /// Evolve every island independently for a generation, on a thread each with the parallel
+    /// feature
+    fn evolve_islands(&mut self) {
+        #[cfg(feature = "parallel")]
+        std::thread::scope(|scope| {
+            for island in self.islands.iter_mut() {
+                scope.spawn(move || island.next());
+            }
+        });
+
+        #[cfg(not(feature = "parallel"))]
+        for island in self.islands.iter_mut() {
+            island.next();
+        }
+    }
+
+    /// Send migrants from every island to its neighbours in the topology, where they replace
+    /// the worst individuals
+    fn migrate(&mut self) {
+        let count = self.islands.len();
+        let migrants = self.config.migrants;
+        let policy = self.config.migration_policy;
+        let rng = &mut self.rng;
+
+        if count < 2 || migrants == 0 {
+            return;
+        }
+
+        let emigrants: Vec<Vec<Individual<T>>> = self
+            .islands
+            .iter()
+            .map(|island| {
+                let individuals = island.individuals();
+                let n = migrants.min(individuals.len());
+
+                // Cellular islands keep their grid order, so the best have to be looked up
+                match policy {
+                    MigrationPolicy::Best => island.fittest(n),
+                    MigrationPolicy::Random => {
+                        individuals.choose_multiple(rng, n).cloned().collect()
+                    }
+                }
+            })
+            .collect();
+
+        let mut incoming: Vec<Vec<Individual<T>>> = vec![Vec::new(); count];
+        for (source, individuals) in emigrants.into_iter().enumerate() {
+            match self.config.migration_topology {
+                MigrationTopology::Ring => incoming[(source + 1) % count].extend(individuals),
+                MigrationTopology::FullyConnected => {
+                    for target in (0..count).filter(|t| *t != source) {
+                        incoming[target].extend(individuals.iter().cloned());
+                    }
+                }
+                MigrationTopology::Random => {
+                    // Pick any island but the source
+                    let mut target = rng.gen_range(0..count - 1);
+                    if target >= source {
+                        target += 1;
+                    }
+                    incoming[target].extend(individuals);
+                }
+            }
+        }
+
+        for (island, individuals) in self.islands.iter_mut().zip(incoming) {
+            if !individuals.is_empty() {
+                island.immigrate(individuals);
+            }
+        }
+    }
+
+    /// Combine the islands into one population and update the statistics from it
+    fn update_stats(&mut self) {
+        self.population = self
+            .islands
+            .iter()
+            .flat_map(|island| island.individuals().iter().cloned())
+            .collect();
+        sort(&mut self.population, !self.config.minimize);
+
+        let mut stats = EvolutionStats {
+            generation: self.islands[0].stats().generation,
+            max_generations: self.config.max_generations,
+            best_fitness: self.stats.best_fitness,
+            last_improvement: self.stats.last_improvement,
+            elapsed: self.started.elapsed().unwrap().as_secs_f32(),
+            ..Default::default()
+        };
+
+        for island in &self.islands {
+            let island = island.stats();
+            stats.mutations += island.mutations;
+            stats.total_mutations += island.total_mutations;
+            stats.crossovers += island.crossovers;
+            stats.total_crossovers += island.total_crossovers;
+            stats.evaluations += island.evaluations;
+        }
+
+        let with_diversity = tracks_diversity(&self.config, &self.observers);
+        update_fitness_stats(
+            &mut stats,
+            &self.population,
+            self.config.minimize,
+            with_diversity,
+        );
+        self.stats = stats;
+    }
+
+    /// Update the statistics after a generation and notify the observers
+    fn notify(&mut self) -> Control {
+        self.update_stats();
+        let improved = self.stats.record_improvement(self.config.minimize);
+
+        notify_observers(&mut self.observers, &self.stats, &self.population, improved)
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.observers.push(observer);
+    }
+
+    /// The best individual on any island, as of the last generation
+    pub fn best(&self) -> &Individual<T> {
+        &self.population[0]
+    }
+
+    pub fn islands(&self) -> &[StandardPopulation<T>] {
+        &self.islands
+    }
+}
+
+impl<T> Population for IslandModel<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    type Genotype = T;
+
+    /// Evolve all islands until the combined population meets the termination criteria
+    fn evolve(&mut self) -> RunResult<T> {
+        self.started = SystemTime::now();
+
+        for island in self.islands.iter_mut() {
+            island.initialize();
+        }
+        self.update_stats();
+        self.stats.record_improvement(self.config.minimize);
+
+        for observer in self.observers.iter_mut() {
+            observer.on_start(&self.config, &self.population);
+        }
+
+        let condition = self.config.termination();
+        let termination = loop {
+            if let Some(reason) = condition.check(&self.stats, self.config.minimize) {
+                break reason;
+            }
+
+            self.evolve_islands();
+            let generation = self.islands[0].stats().generation as u32;
+            if generation.is_multiple_of(self.config.migration_interval.max(1)) {
+                self.migrate();
+            }
+
+            if self.notify() == Control::Stop {
+                break TerminationReason::Stopped;
+            }
+        };
+
+        let result = RunResult {
+            best: self.population[0].clone(),
+            stats: self.stats.clone(),
+            generations: self.stats.generation,
+            elapsed: self.started.elapsed().unwrap(),
+            evaluations: self.stats.evaluations,
+            termination,
+            pareto_front: Vec::new(),
+            hypervolume: None,
+        };
+
+        for observer in self.observers.iter_mut() {
+            observer.on_finish(&result);
+        }
+
+        result
+    }
+
+    /// Create the islands from a single seed
+    fn new(config: Config, context: T::Context) -> Self {
+        let rng = create_rng(config.seed);
+        IslandModel::from(StandardPopulation::with_rng(config, context, rng))
+    }
+}
+
+#[test]
+fn test_seeded_island_runs_are_identical() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let run = || {
+        GeneticAlgorithm::<NQueens>::builder()
+            .seed(11)
+            .target_fitness(2.0)
+            .max_generations(25)
+            .islands(4)
+            .migration_interval(10)
+            .migration_topology(MigrationTopology::Random)
+            .migration_policy(MigrationPolicy::Random)
+            .build_islands(16)
+    };
+    let mut a = run();
+    let mut b = run();
+    let result = a.evolve();
+    b.evolve();
+
+    assert_eq!(result.termination, TerminationReason::MaxGenerations);
+    assert_eq!(result.generations, 25);
+    assert_eq!(a.population.len(), 4 * 50);
+    for (a, b) in a.islands().iter().zip(b.islands()) {
+        assert_eq!(a.individuals(), b.individuals());
+    }
+}
+
+#[test]
+fn test_island_termination_is_checked_every_generation() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    // Each of the 4 islands evaluates its 50 individuals initially and every generation
+    let result = GeneticAlgorithm::<NQueens>::builder()
+        .seed(12)
+        .target_fitness(2.0)
+        .max_evaluations(4 * 50 * 6)
+        .islands(4)
+        .migration_interval(20)
+        .build_islands(16)
+        .evolve();
+
+    assert_eq!(result.termination, TerminationReason::MaxEvaluations);
+    assert_eq!(result.generations, 5);
+}
+
+#[test]
+fn test_cellular_islands_send_their_best() {
+    use super::builder::GeneticAlgorithm;
+    use super::population::PopulationModel;
+    use crate::problems::NQueens;
+
+    let mut model = GeneticAlgorithm::<NQueens>::builder()
+        .seed(13)
+        .population(25)
+        .population_model(PopulationModel::Cellular)
+        .target_fitness(2.0)
+        .max_generations(3)
+        .islands(2)
+        .migrants(5)
+        .build_islands(12);
+    model.evolve();
+
+    for island in model.islands() {
+        let mut ranked = island.individuals().to_vec();
+        sort(&mut ranked, true);
+        let emigrants = island.fittest(5);
+
+        assert_eq!(emigrants, ranked[..5].to_vec());
+        assert_ne!(island.individuals()[..5].to_vec(), emigrants);
+    }
+}
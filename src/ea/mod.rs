@@ -1,7 +1,33 @@
+pub mod builder;
+pub mod cellular;
+pub mod checkpoint;
+pub mod export;
 pub mod individual;
+pub mod island;
+pub mod moead;
+pub mod observer;
+pub mod pareto;
 pub mod population;
+pub mod report;
+pub mod spea2;
+pub mod termination;
 
-pub use individual::{Genotype, Individual, Phenotype};
+pub use builder::{Config, ConfigError, GeneticAlgorithm, GeneticAlgorithmBuilder};
+pub use cellular::{CellularUpdate, Neighbourhood};
+pub use checkpoint::checkpoint_config;
+pub use export::{StatsFormat, StatsWriter};
+pub use individual::{Genotype, Individual, MaybeSend, MaybeSync, MultiObjective, Phenotype};
+pub use island::{IslandModel, MigrationPolicy, MigrationTopology};
+pub use moead::{Aggregation, MoeaD};
+pub use observer::{Control, Observer};
+pub use pareto::{
+    check_reference_point, crowding_distance, dominates, hypervolume, non_dominated_sort,
+    pareto_front, MultiObjectiveAlgorithm, Nsga2,
+};
 pub use population::{
-    ParentSelection, Population, PopulationModel, StandardPopulation, SurvivorSelection,
+    create_rng, EvolutionRng, EvolutionStats, LocalSearch, ParentSelection, Population,
+    PopulationModel, StandardPopulation, SurvivorSelection,
 };
+pub use report::{ConsoleReporter, RunResult, TerminationReason};
+pub use spea2::Spea2;
+pub use termination::Termination;
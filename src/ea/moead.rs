@@ -0,0 +1,332 @@
+/// MOEA/D, decomposing a multi-objective problem into scalar subproblems along weight vectors
+use super::builder::Config;
+use super::individual::{Genotype, Individual, MultiObjective};
+use super::observer::{Control, Observer};
+use super::pareto::Engine;
+use super::population::{create_rng, EvolutionStats, Population, StandardPopulation};
+use super::report::{RunResult, TerminationReason};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::time::SystemTime;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// How the objectives of a subproblem are combined into one value
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    pub enum Aggregation {
+        Tchebycheff,
+        PenaltyBoundaryIntersection,
+    }
+}
+
+/// Scalar value of objectives for the subproblem of a weight vector, lower is better. The
+/// objectives and the ideal point are values to be minimized.
+fn aggregate(
+    aggregation: Aggregation,
+    penalty: f64,
+    objectives: &[f64],
+    weights: &[f64],
+    ideal: &[f64],
+) -> f64 {
+    let offsets = objectives.iter().zip(ideal).map(|(f, z)| f - z);
+
+    match aggregation {
+        // Largest weighted distance from the ideal point, zero weights would ignore an objective
+        Aggregation::Tchebycheff => offsets
+            .zip(weights)
+            .map(|(d, w)| w.max(1e-6) * d.abs())
+            .fold(0.0, f64::max),
+        // Distance along the weight vector plus a penalty on the distance from it
+        Aggregation::PenaltyBoundaryIntersection => {
+            let offsets: Vec<f64> = offsets.collect();
+            let norm = weights.iter().map(|w| w * w).sum::<f64>().sqrt();
+            let along = offsets.iter().zip(weights).map(|(d, w)| d * w).sum::<f64>() / norm;
+            let away = offsets
+                .iter()
+                .zip(weights)
+                .map(|(d, w)| (d - along * w / norm).powi(2))
+                .sum::<f64>()
+                .sqrt();
+
+            along + penalty * away
+        }
+    }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).map(|i| (n - i) as f64 / (i + 1) as f64).product()
+}
+
+/// All ways of splitting h units over the given number of parts
+fn compositions(h: usize, parts: usize) -> Vec<Vec<usize>> {
+    if parts == 1 {
+        return vec![vec![h]];
+    }
+
+    (0..=h)
+        .flat_map(|first| {
+            compositions(h - first, parts - 1)
+                .into_iter()
+                .map(move |rest| [vec![first], rest].concat())
+        })
+        .collect()
+}
+
+/// n weight vectors summing to 1 for the given number of objectives. The simplex lattice with
+/// the most vectors not exceeding n spreads them evenly, and random vectors make up the rest.
+pub fn weight_vectors(n: usize, objectives: usize, rng: &mut impl Rng) -> Vec<Vec<f64>> {
+    if objectives < 2 {
+        return vec![vec![1.0; objectives]; n];
+    }
+
+    let mut h = 0;
+    while binomial(h + objectives, objectives - 1) <= n as f64 {
+        h += 1;
+    }
+
+    let mut weights: Vec<Vec<f64>> = if h == 0 {
+        Vec::new()
+    } else {
+        compositions(h, objectives)
+            .into_iter()
+            .map(|c| c.into_iter().map(|u| u as f64 / h as f64).collect())
+            .collect()
+    };
+
+    while weights.len() < n {
+        let random: Vec<f64> = (0..objectives).map(|_| rng.gen::<f64>()).collect();
+        let sum: f64 = random.iter().sum();
+        weights.push(random.into_iter().map(|w| w / sum.max(1e-12)).collect());
+    }
+
+    weights
+}
+
+/// MOEA/D by Zhang and Li. Every individual solves the subproblem of its own weight vector, and
+/// its offspring replaces the individuals of the neighbouring subproblems it does better on.
+/// Offspring of all subproblems are bred from the same generation and evaluated together, so
+/// evaluations can run in parallel, before they replace their neighbours in turn.
+pub struct MoeaD<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    engine: Engine<T>,
+    /// Current solution of every subproblem
+    population: Vec<Individual<T>>,
+    /// Weight vector of every subproblem
+    weights: Vec<Vec<f64>>,
+    /// Subproblems with the closest weight vectors to every subproblem, itself included
+    neighbours: Vec<Vec<usize>>,
+    /// Best value of every objective found so far, negated when maximizing
+    ideal: Vec<f64>,
+}
+
+/// Evolve the individuals of a population with MOEA/D, taking over its observers
+impl<T> From<StandardPopulation<T>> for MoeaD<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    fn from(population: StandardPopulation<T>) -> Self {
+        let (engine, population) = Engine::new(population);
+
+        MoeaD {
+            engine,
+            population,
+            weights: Vec::new(),
+            neighbours: Vec::new(),
+            ideal: Vec::new(),
+        }
+    }
+}
+
+impl<T> MoeaD<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    /// Objectives of an individual as values to be minimized
+    fn minimized(&self, individual: &Individual<T>) -> Vec<f64> {
+        let sign = if self.engine.config.minimize {
+            1.0
+        } else {
+            -1.0
+        };
+        individual.objectives.iter().map(|o| sign * o).collect()
+    }
+
+    /// Value of an individual for the subproblem with the given index, lower is better
+    fn aggregate(&self, objectives: &[f64], subproblem: usize) -> f64 {
+        let config = &self.engine.config;
+        aggregate(
+            config.aggregation,
+            config.pbi_penalty,
+            objectives,
+            &self.weights[subproblem],
+            &self.ideal,
+        )
+    }
+
+    /// Move the ideal point to any better objectives
+    fn update_ideal(&mut self, objectives: &[f64]) {
+        for (z, f) in self.ideal.iter_mut().zip(objectives) {
+            *z = z.min(*f);
+        }
+    }
+
+    /// Evaluate the initial population and assign the weight vectors and their neighbourhoods
+    fn initialize(&mut self) {
+        let mut population = std::mem::take(&mut self.population);
+        self.engine.evaluate(&mut population);
+        self.population = population;
+
+        let n = self.population.len();
+        let count = self.population.first().map_or(0, |i| i.objectives.len());
+        self.weights = weight_vectors(n, count, &mut self.engine.rng);
+
+        let size = self.engine.config.weight_neighbours.clamp(1, n.max(1));
+        let weights = &self.weights;
+        self.neighbours = (0..n)
+            .map(|i| {
+                let distance = |j: usize| {
+                    weights[i]
+                        .iter()
+                        .zip(&weights[j])
+                        .map(|(a, b)| (a - b).powi(2))
+                        .sum::<f64>()
+                };
+                let mut closest: Vec<usize> = (0..n).collect();
+                closest.sort_by(|&a, &b| {
+                    distance(a)
+                        .partial_cmp(&distance(b))
+                        .unwrap_or(Ordering::Equal)
+                });
+                closest.truncate(size);
+                closest
+            })
+            .collect();
+
+        self.ideal = vec![f64::INFINITY; count];
+        for i in 0..n {
+            let objectives = self.minimized(&self.population[i]);
+            self.update_ideal(&objectives);
+        }
+    }
+
+    /// Breed an offspring for every subproblem from two of its neighbours and let it replace
+    /// the neighbours it improves on, returning whether any observer wants to stop
+    fn next(&mut self) -> Control {
+        let mut pool = Vec::with_capacity(2 * self.population.len());
+        for neighbours in &self.neighbours {
+            for _ in 0..2 {
+                pool.push(neighbours[self.engine.rng.gen_range(0..neighbours.len())]);
+            }
+        }
+
+        let mut offspring = self.engine.breed(&self.population, &pool);
+        self.engine.evaluate(&mut offspring);
+
+        for (subproblem, child) in offspring.into_iter().enumerate() {
+            let objectives = self.minimized(&child);
+            self.update_ideal(&objectives);
+
+            for k in 0..self.neighbours[subproblem].len() {
+                let j = self.neighbours[subproblem][k];
+                let current = self.minimized(&self.population[j]);
+
+                if self.aggregate(&objectives, j) <= self.aggregate(&current, j) {
+                    self.population[j] = child.clone();
+                }
+            }
+        }
+
+        self.engine.notify(&self.population)
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.engine.add_observer(observer);
+    }
+
+    /// Current solution of every subproblem
+    pub fn individuals(&self) -> &[Individual<T>] {
+        &self.population
+    }
+
+    /// Weight vector of every subproblem, in the same order as the individuals
+    pub fn weights(&self) -> &[Vec<f64>] {
+        &self.weights
+    }
+
+    pub fn stats(&self) -> &EvolutionStats {
+        &self.engine.stats
+    }
+}
+
+impl<T> Population for MoeaD<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    type Genotype = T;
+
+    /// Evolve the subproblems until the scalar fitness meets the termination criteria,
+    /// returning the Pareto front of their solutions along with the individual of best fitness
+    fn evolve(&mut self) -> RunResult<T> {
+        self.engine.started = SystemTime::now();
+        self.initialize();
+        self.engine.start(&self.population);
+
+        let termination = loop {
+            if let Some(reason) = self.engine.check() {
+                break reason;
+            }
+            if self.next() == Control::Stop {
+                break TerminationReason::Stopped;
+            }
+        };
+
+        self.engine.finish(&self.population, termination)
+    }
+
+    /// Create a new MOEA/D population
+    fn new(config: Config, context: T::Context) -> Self {
+        let rng = create_rng(config.seed);
+        MoeaD::from(StandardPopulation::with_rng(config, context, rng))
+    }
+}
+
+#[test]
+fn test_weight_vectors() {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+    let weights = weight_vectors(5, 2, &mut rng);
+    assert_eq!(weights[0], vec![0.0, 1.0]);
+    assert_eq!(weights[2], vec![0.5, 0.5]);
+    assert_eq!(weights[4], vec![1.0, 0.0]);
+
+    // 15 lattice points for 3 objectives with 4 units each, topped up with random weights
+    let weights = weight_vectors(17, 3, &mut rng);
+    assert_eq!(weights.len(), 17);
+    assert!(weights
+        .iter()
+        .all(|w| (w.iter().sum::<f64>() - 1.0).abs() < 1e-9));
+
+    let ideal = [0.0, 0.0];
+    let tchebycheff = Aggregation::Tchebycheff;
+    assert_eq!(
+        aggregate(tchebycheff, 0.0, &[1.0, 3.0], &[0.5, 0.5], &ideal),
+        1.5
+    );
+    // On the weight vector there is no penalty, (1, 0) is off it by the same distance as along
+    let pbi = Aggregation::PenaltyBoundaryIntersection;
+    let along = aggregate(pbi, 5.0, &[1.0, 1.0], &[0.5, 0.5], &ideal);
+    assert!((along - 2f64.sqrt()).abs() < 1e-9);
+    let away = aggregate(pbi, 5.0, &[1.0, 0.0], &[0.5, 0.5], &ideal);
+    assert!((away - 6.0 * 0.5f64.sqrt()).abs() < 1e-9);
+}
@@ -0,0 +1,40 @@
+/// Hooks for following and controlling an evolution
+use super::builder::Config;
+use super::individual::{Genotype, Individual, MaybeSend};
+use super::population::EvolutionStats;
+use super::report::RunResult;
+
+/// Whether an evolution should continue after a generation
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+/// Receives events during an evolution. The population is always sorted with the best
+/// individual first. All methods have empty default implementations. With the parallel feature
+/// observers must be Send, so populations can move between threads.
+pub trait Observer<T>: MaybeSend
+where
+    T: Genotype + PartialOrd,
+{
+    /// Called once the initial population has been evaluated
+    fn on_start(&mut self, _config: &Config, _population: &[Individual<T>]) {}
+
+    /// Called after every generation, returning Control::Stop ends the evolution
+    fn on_generation(&mut self, _stats: &EvolutionStats, _population: &[Individual<T>]) -> Control {
+        Control::Continue
+    }
+
+    /// Whether on_generation uses the diversity of the population, which is only computed when
+    /// something needs it
+    fn needs_diversity(&self) -> bool {
+        false
+    }
+
+    /// Called whenever a generation improves on the best fitness found so far
+    fn on_improvement(&mut self, _stats: &EvolutionStats, _best: &Individual<T>) {}
+
+    /// Called with the outcome when the evolution stops
+    fn on_finish(&mut self, _result: &RunResult<T>) {}
+}
@@ -0,0 +1,630 @@
+/// Multi-objective optimisation, keeping a population of trade-offs between the objectives
+use super::builder::{Config, ConfigError};
+use super::individual::{Genotype, Individual, MultiObjective};
+use super::observer::{Control, Observer};
+use super::population::{
+    breed, create_rng, notify_observers, sort, tracks_diversity, update_fitness_stats, Evaluator,
+    EvolutionRng, EvolutionStats, Population, StandardPopulation,
+};
+use super::report::{RunResult, TerminationReason};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::SystemTime;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Engines for evolving problems with several objectives
+    #[derive(Copy, Clone, Debug)]
+    pub enum MultiObjectiveAlgorithm {
+        Nsga2,
+        Spea2,
+        MoeaD,
+    }
+}
+
+/// Whether objectives a are at least as good as b in every objective and better in one
+pub fn dominates(a: &[f64], b: &[f64], minimize: bool) -> bool {
+    let mut better = false;
+
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = if minimize { (x, y) } else { (y, x) };
+        if x > y {
+            return false;
+        }
+        if x < y {
+            better = true;
+        }
+    }
+
+    better
+}
+
+/// Split the objective vectors into fronts of indices, the first front holding the
+/// non-dominated ones, the second those only dominated by the first front and so on
+pub fn non_dominated_sort<O: AsRef<[f64]>>(objectives: &[O], minimize: bool) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut dominators = vec![0; n];
+
+    for p in 0..n {
+        for q in p + 1..n {
+            let (a, b) = (objectives[p].as_ref(), objectives[q].as_ref());
+            if dominates(a, b, minimize) {
+                dominated[p].push(q);
+                dominators[q] += 1;
+            } else if dominates(b, a, minimize) {
+                dominated[q].push(p);
+                dominators[p] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..n).filter(|&p| dominators[p] == 0).collect();
+
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &p in &front {
+            for &q in &dominated[p] {
+                dominators[q] -= 1;
+                if dominators[q] == 0 {
+                    next.push(q);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next;
+    }
+
+    fronts
+}
+
+/// Crowding distance of every member of a front, the sum over the objectives of the
+/// normalized distance between its neighbours. The extremes of every objective get an infinite
+/// distance so they are always kept.
+pub fn crowding_distance<O: AsRef<[f64]>>(objectives: &[O], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.0; front.len()];
+    let count = front.first().map_or(0, |&i| objectives[i].as_ref().len());
+
+    for m in 0..count {
+        let value = |k: usize| objectives[front[k]].as_ref()[m];
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|&a, &b| value(a).partial_cmp(&value(b)).unwrap_or(Ordering::Equal));
+
+        let (first, last) = (order[0], order[order.len() - 1]);
+        distances[first] = f64::INFINITY;
+        distances[last] = f64::INFINITY;
+
+        let range = value(last) - value(first);
+        if range <= 0.0 || !range.is_finite() {
+            continue;
+        }
+        for k in order.windows(3) {
+            distances[k[1]] += (value(k[2]) - value(k[0])) / range;
+        }
+    }
+
+    distances
+}
+
+/// Hypervolume of the region dominated by the points and bounded by the reference point, which
+/// should be worse than the points in every objective. Points that are not strictly better than
+/// the reference point in every objective add nothing.
+pub fn hypervolume<O: AsRef<[f64]>>(points: &[O], reference: &[f64], minimize: bool) -> f64 {
+    // Measure everything as values to be minimized
+    let sign = if minimize { 1.0 } else { -1.0 };
+    let reference: Vec<f64> = reference.iter().map(|r| sign * r).collect();
+    let points: Vec<Vec<f64>> = points
+        .iter()
+        .map(|p| p.as_ref().iter().map(|x| sign * x).collect::<Vec<f64>>())
+        .filter(|p| p.len() == reference.len() && p.iter().zip(&reference).all(|(x, r)| x < r))
+        .collect();
+
+    sliced_volume(points, &reference)
+}
+
+/// Hypervolume of minimized points within the reference point, summing the slices between the
+/// points along the last objective
+fn sliced_volume(mut points: Vec<Vec<f64>>, reference: &[f64]) -> f64 {
+    let m = reference.len();
+
+    if points.is_empty() || m == 0 {
+        return 0.0;
+    }
+    if m == 1 {
+        let best = points.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min);
+        return reference[0] - best;
+    }
+
+    points.sort_by(|a, b| a[m - 1].partial_cmp(&b[m - 1]).unwrap_or(Ordering::Equal));
+
+    let mut volume = 0.0;
+    for (i, point) in points.iter().enumerate() {
+        let upper = points.get(i + 1).map_or(reference[m - 1], |p| p[m - 1]);
+        let depth = upper - point[m - 1];
+
+        if depth > 0.0 {
+            let slice = points[..=i].iter().map(|p| p[..m - 1].to_vec()).collect();
+            volume += depth * sliced_volume(slice, &reference[..m - 1]);
+        }
+    }
+
+    volume
+}
+
+/// The distinct non-dominated individuals of a population, sorted by their objectives
+pub fn pareto_front<T>(population: &[Individual<T>], minimize: bool) -> Vec<Individual<T>>
+where
+    T: Genotype + PartialOrd,
+{
+    let objectives: Vec<&[f64]> = population.iter().map(|i| i.objectives.as_slice()).collect();
+    let mut front: Vec<Individual<T>> = match non_dominated_sort(&objectives, minimize).first() {
+        Some(front) => front.iter().map(|&i| population[i].clone()).collect(),
+        None => Vec::new(),
+    };
+
+    front.sort_by(|a, b| {
+        a.objectives
+            .partial_cmp(&b.objectives)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                a.genotype
+                    .partial_cmp(&b.genotype)
+                    .unwrap_or(Ordering::Equal)
+            })
+    });
+    front.dedup_by(|a, b| a.genotype == b.genotype);
+    front
+}
+
+/// Reference point for the hypervolume of a front when none is configured: the nadir point,
+/// the worst value of every objective on the front, moved away by 10% of the range of that
+/// objective, or by 1 if all points agree on it, so the extremes of the front count too
+fn default_reference(front: &[&[f64]], minimize: bool) -> Vec<f64> {
+    let count = front.first().map_or(0, |o| o.len());
+
+    (0..count)
+        .map(|m| {
+            let (low, high) = front
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |r, o| {
+                    (r.0.min(o[m]), r.1.max(o[m]))
+                });
+            let offset = if high > low { 0.1 * (high - low) } else { 1.0 };
+
+            if minimize {
+                high + offset
+            } else {
+                low - offset
+            }
+        })
+        .collect()
+}
+
+/// Check that the configured reference point, if any, has one value for every objective of
+/// the problem, before a multi-objective engine evolves the population
+pub fn check_reference_point<T>(population: &StandardPopulation<T>) -> Result<(), ConfigError>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    let reference = match &population.config().reference_point {
+        Some(reference) => reference,
+        None => return Ok(()),
+    };
+    let context = population.context();
+    let count = match population.individuals().first() {
+        Some(individual) => individual
+            .genotype
+            .decode(context)
+            .objectives(context)
+            .len(),
+        None => return Ok(()),
+    };
+
+    if reference.len() == count {
+        Ok(())
+    } else {
+        Err(ConfigError(format!(
+            "reference point {:?} does not have one value for each of the {} objectives",
+            reference, count
+        )))
+    }
+}
+
+/// Settings, statistics and observers shared by the multi-objective engines, which differ only
+/// in how they select parents and survivors
+pub(crate) struct Engine<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    pub(crate) config: Config,
+    pub(crate) context: Arc<T::Context>,
+    pub(crate) rng: EvolutionRng,
+    pub(crate) stats: EvolutionStats,
+    pub(crate) started: SystemTime,
+    evaluator: Evaluator,
+    observers: Vec<Box<dyn Observer<T>>>,
+}
+
+impl<T> Engine<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    /// Take over the settings, context, generator and observers of a population, along with its
+    /// individuals
+    pub(crate) fn new(population: StandardPopulation<T>) -> (Self, Vec<Individual<T>>) {
+        if let Err(e) = check_reference_point(&population) {
+            panic!("{}", e);
+        }
+
+        let (config, context, rng, population, observers) = population.into_parts();
+        let engine = Engine {
+            stats: EvolutionStats {
+                max_generations: config.max_generations,
+                ..Default::default()
+            },
+            evaluator: Evaluator::new(config.threads),
+            config,
+            context,
+            rng,
+            started: SystemTime::now(),
+            observers,
+        };
+
+        (engine, population)
+    }
+
+    /// Evaluate the fitness and objectives of the given individuals
+    pub(crate) fn evaluate(&mut self, individuals: &mut [Individual<T>]) {
+        self.stats.evaluations +=
+            self.evaluator
+                .evaluate(individuals, self.config.local_search, &self.context);
+
+        for individual in individuals.iter_mut() {
+            individual.evaluate_objectives(&self.context);
+        }
+    }
+
+    /// Start the next generation by breeding one offspring for every pair of parent indices in
+    /// the pool
+    pub(crate) fn breed(
+        &mut self,
+        parents: &[Individual<T>],
+        pool: &[usize],
+    ) -> Vec<Individual<T>> {
+        self.stats.generation += 1;
+        self.stats.mutations = 0;
+        self.stats.crossovers = 0;
+
+        breed(
+            parents,
+            pool,
+            &self.config,
+            &self.context,
+            &mut self.rng,
+            &mut self.stats,
+        )
+    }
+
+    /// Copy of the individuals sorted with the best fitness first, as observers expect
+    fn sorted(&self, population: &[Individual<T>]) -> Vec<Individual<T>> {
+        let mut sorted = population.to_vec();
+        sort(&mut sorted, !self.config.minimize);
+        sorted
+    }
+
+    /// Update the statistics of the evaluated initial population and notify the observers
+    pub(crate) fn start(&mut self, population: &[Individual<T>]) {
+        let with_diversity = tracks_diversity(&self.config, &self.observers);
+        update_fitness_stats(
+            &mut self.stats,
+            population,
+            self.config.minimize,
+            with_diversity,
+        );
+        self.stats.record_improvement(self.config.minimize);
+
+        let sorted = self.sorted(population);
+        for observer in self.observers.iter_mut() {
+            observer.on_start(&self.config, &sorted);
+        }
+    }
+
+    /// Whether the evolution should stop before the next generation
+    pub(crate) fn check(&self) -> Option<TerminationReason> {
+        self.config
+            .termination()
+            .check(&self.stats, self.config.minimize)
+    }
+
+    /// Update the statistics after a generation and notify the observers, returning whether any
+    /// of them wants to stop
+    pub(crate) fn notify(&mut self, population: &[Individual<T>]) -> Control {
+        let with_diversity = tracks_diversity(&self.config, &self.observers);
+        update_fitness_stats(
+            &mut self.stats,
+            population,
+            self.config.minimize,
+            with_diversity,
+        );
+        self.stats.total_mutations += self.stats.mutations;
+        self.stats.total_crossovers += self.stats.crossovers;
+        self.stats.elapsed = self.started.elapsed().unwrap().as_secs_f32();
+
+        // Survivor selection by objectives can drop the individual of best fitness, which
+        // should not count as an improvement once it is found again
+        let improved = self.stats.record_improvement(self.config.minimize);
+        let sorted = self.sorted(population);
+
+        notify_observers(&mut self.observers, &self.stats, &sorted, improved)
+    }
+
+    /// Collect the outcome with the Pareto front of the final population and its hypervolume,
+    /// and notify the observers
+    pub(crate) fn finish(
+        &mut self,
+        population: &[Individual<T>],
+        termination: TerminationReason,
+    ) -> RunResult<T> {
+        let minimize = self.config.minimize;
+        let front = pareto_front(population, minimize);
+        let objectives: Vec<&[f64]> = front.iter().map(|i| i.objectives.as_slice()).collect();
+        let reference = match &self.config.reference_point {
+            Some(point) => point.clone(),
+            None => default_reference(&objectives, minimize),
+        };
+        let hypervolume = Some(hypervolume(&objectives, &reference, minimize));
+
+        let result = RunResult {
+            best: self.sorted(population)[0].clone(),
+            stats: self.stats.clone(),
+            generations: self.stats.generation,
+            elapsed: self.started.elapsed().unwrap(),
+            evaluations: self.stats.evaluations,
+            termination,
+            pareto_front: front,
+            hypervolume,
+        };
+
+        for observer in self.observers.iter_mut() {
+            observer.on_finish(&result);
+        }
+
+        result
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub(crate) fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.observers.push(observer);
+    }
+}
+
+/// Indices of the given individuals sorted into non-dominated fronts, and the rank and crowding
+/// distance of each
+fn rank_and_crowd<T>(population: &[Individual<T>], minimize: bool) -> Vec<(usize, usize, f64)>
+where
+    T: Genotype + PartialOrd,
+{
+    let objectives: Vec<&[f64]> = population.iter().map(|i| i.objectives.as_slice()).collect();
+    let mut ranked = Vec::with_capacity(population.len());
+
+    for (rank, front) in non_dominated_sort(&objectives, minimize)
+        .into_iter()
+        .enumerate()
+    {
+        let distances = crowding_distance(&objectives, &front);
+        let mut members: Vec<(usize, usize, f64)> = front
+            .into_iter()
+            .zip(distances)
+            .map(|(i, distance)| (i, rank, distance))
+            .collect();
+        members.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal));
+        ranked.extend(members);
+    }
+
+    ranked
+}
+
+/// NSGA-II by Deb et al. Parents are chosen by binary tournaments on rank and crowding
+/// distance, and the next generation is the best of parents and offspring by the same order.
+/// All objectives are minimized or maximized together, following the minimize setting, while
+/// the statistics and the best individual follow the scalar fitness.
+pub struct Nsga2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    engine: Engine<T>,
+    /// Individuals sorted by rank, then by decreasing crowding distance
+    population: Vec<Individual<T>>,
+    /// Non-dominated rank of each individual, 0 for the Pareto front
+    rank: Vec<usize>,
+    /// Crowding distance of each individual within its front
+    crowding: Vec<f64>,
+}
+
+/// Evolve the individuals of a population with NSGA-II, taking over its observers
+impl<T> From<StandardPopulation<T>> for Nsga2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    fn from(population: StandardPopulation<T>) -> Self {
+        let (engine, population) = Engine::new(population);
+
+        Nsga2 {
+            engine,
+            population,
+            rank: Vec::new(),
+            crowding: Vec::new(),
+        }
+    }
+}
+
+impl<T> Nsga2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    /// Keep the best individuals by rank and crowding distance, at most the population size
+    fn select_survivors(&mut self, candidates: Vec<Individual<T>>) {
+        let mut ranked = rank_and_crowd(&candidates, self.engine.config.minimize);
+        ranked.truncate(self.engine.config.population);
+
+        // Crowding distances of the last front are not recomputed after truncation, as in the
+        // original algorithm
+        let mut candidates: Vec<Option<Individual<T>>> = candidates.into_iter().map(Some).collect();
+        self.population = ranked
+            .iter()
+            .filter_map(|&(i, _, _)| candidates[i].take())
+            .collect();
+        self.rank = ranked.iter().map(|&(_, rank, _)| rank).collect();
+        self.crowding = ranked.iter().map(|&(_, _, distance)| distance).collect();
+    }
+
+    /// Binary tournament preferring the lower rank, then the larger crowding distance
+    fn crowded_tournament(&mut self) -> usize {
+        let n = self.population.len();
+        let a = self.engine.rng.gen_range(0..n);
+        let b = self.engine.rng.gen_range(0..n);
+
+        match self.rank[a].cmp(&self.rank[b]) {
+            Ordering::Less => a,
+            Ordering::Greater => b,
+            Ordering::Equal if self.crowding[b] > self.crowding[a] => b,
+            Ordering::Equal => a,
+        }
+    }
+
+    /// Evaluate the initial population and sort it into fronts
+    fn initialize(&mut self) {
+        let mut population = std::mem::take(&mut self.population);
+        self.engine.evaluate(&mut population);
+        self.select_survivors(population);
+    }
+
+    /// Breed a generation of offspring and keep the best of parents and offspring, returning
+    /// whether any observer wants to stop
+    fn next(&mut self) -> Control {
+        let pool: Vec<usize> = (0..2 * self.engine.config.population)
+            .map(|_| self.crowded_tournament())
+            .collect();
+        let mut offspring = self.engine.breed(&self.population, &pool);
+        self.engine.evaluate(&mut offspring);
+
+        let mut candidates = std::mem::take(&mut self.population);
+        candidates.extend(offspring);
+        self.select_survivors(candidates);
+
+        self.engine.notify(&self.population)
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.engine.add_observer(observer);
+    }
+
+    /// Individuals sorted by rank, then by decreasing crowding distance
+    pub fn individuals(&self) -> &[Individual<T>] {
+        &self.population
+    }
+
+    /// The distinct non-dominated individuals of the population, sorted by their objectives
+    pub fn pareto_front(&self) -> Vec<Individual<T>> {
+        pareto_front(&self.population, self.engine.config.minimize)
+    }
+
+    pub fn stats(&self) -> &EvolutionStats {
+        &self.engine.stats
+    }
+}
+
+impl<T> Population for Nsga2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    type Genotype = T;
+
+    /// Evolve the population until the scalar fitness meets the termination criteria,
+    /// returning the final Pareto front along with the individual of best fitness
+    fn evolve(&mut self) -> RunResult<T> {
+        self.engine.started = SystemTime::now();
+        self.initialize();
+        self.engine.start(&self.population);
+
+        let termination = loop {
+            if let Some(reason) = self.engine.check() {
+                break reason;
+            }
+            if self.next() == Control::Stop {
+                break TerminationReason::Stopped;
+            }
+        };
+
+        self.engine.finish(&self.population, termination)
+    }
+
+    /// Create a new NSGA-II population
+    fn new(config: Config, context: T::Context) -> Self {
+        let rng = create_rng(config.seed);
+        Nsga2::from(StandardPopulation::with_rng(config, context, rng))
+    }
+}
+
+#[test]
+fn test_non_dominated_sort() {
+    let objectives = vec![
+        vec![1.0, 5.0],
+        vec![2.0, 2.0],
+        vec![5.0, 1.0],
+        vec![3.0, 3.0],
+        vec![4.0, 4.0],
+        vec![2.0, 2.0],
+    ];
+
+    assert!(dominates(&objectives[1], &objectives[3], true));
+    assert!(!dominates(&objectives[1], &objectives[5], true));
+    assert!(dominates(&objectives[3], &objectives[1], false));
+
+    let fronts = non_dominated_sort(&objectives, true);
+    assert_eq!(fronts, vec![vec![0, 1, 2, 5], vec![3], vec![4]]);
+
+    let distances = crowding_distance(&objectives, &fronts[0]);
+    assert_eq!(distances[0], f64::INFINITY);
+    assert_eq!(distances[2], f64::INFINITY);
+    // The two copies of (2, 2) split the gap between the extremes of each objective
+    assert_eq!(distances[1] + distances[3], 2.0);
+}
+
+#[test]
+fn test_hypervolume() {
+    let front = vec![
+        vec![1.0, 3.0],
+        vec![2.0, 2.0],
+        vec![3.0, 1.0],
+        vec![3.0, 3.0],
+    ];
+
+    // Three overlapping boxes within (4, 4), the dominated point adds nothing
+    assert_eq!(hypervolume(&front, &[4.0, 4.0], true), 6.0);
+    let maximized: Vec<Vec<f64>> = front.iter().map(|p| vec![-p[0], -p[1]]).collect();
+    assert_eq!(hypervolume(&maximized, &[-4.0, -4.0], false), 6.0);
+
+    // The default reference lies beyond the nadir (3, 3) of the front, so the extremes add
+    // boxes of 1 x 0.2 and 0.2 x 2.2 to the 1 x 1.2 of the middle point
+    let front: Vec<&[f64]> = front[..3].iter().map(|p| p.as_slice()).collect();
+    let reference = default_reference(&front, true);
+    assert_eq!(reference, vec![3.2, 3.2]);
+    assert!((hypervolume(&front, &reference, true) - 1.84).abs() < 1e-9);
+
+    // A unit cube and a box of 1 x 2 x 0.5 overlapping it by half
+    let points = vec![vec![0.0, 0.0, 0.0], vec![0.0, -1.0, 0.5]];
+    assert_eq!(hypervolume(&points, &[1.0, 1.0, 1.0], true), 1.5);
+}
@@ -1,33 +1,63 @@
+use super::builder::Config;
+use super::cellular::{neighbourhoods, CellularUpdate};
+use super::checkpoint::Checkpoint;
 use super::individual::{Genotype, Individual, Phenotype};
-use crate::Options;
-use rand::{thread_rng, Rng};
-use std::time::SystemTime;
+use super::observer::{Control, Observer};
+use super::report::{RunResult, TerminationReason};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use std::{cmp::Ordering, fmt};
 use std::{fmt::Display, slice::IterMut};
 use structopt::clap::arg_enum;
 
+/// Random number generator driving an evolution, seedable to make runs reproducible
+pub type EvolutionRng = ChaCha8Rng;
+
+/// Create a random number generator from the given seed, or from entropy if there is none
+pub fn create_rng(seed: Option<u64>) -> EvolutionRng {
+    match seed {
+        Some(seed) => EvolutionRng::seed_from_u64(seed),
+        None => EvolutionRng::from_entropy(),
+    }
+}
+
 /// Interface for working with various populations
 pub trait Population {
-    /// Create a new population based on the given options
-    fn new(options: Options) -> Self;
-    /// Remove this?
-    fn evolve(&mut self);
+    /// The genotype of the individuals in the population
+    type Genotype: Genotype + PartialOrd;
+
+    /// Create a new population based on the given settings
+    fn new(config: Config, context: <Self::Genotype as Genotype>::Context) -> Self;
+    /// Evolve the population until it meets its termination criteria
+    fn evolve(&mut self) -> RunResult<Self::Genotype>;
 }
 
 // These are wrapped in arg_enum since we are constructing these directly from StructOpt
 arg_enum! {
     /// Available population models
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
     pub enum PopulationModel {
         SteadyState,
         Generational,
+        MuPlusLambda,
+        MuCommaLambda,
+        Cellular,
     }
 }
 
 // These are wrapped in arg_enum since we are constructing these directly from StructOpt
 arg_enum! {
     /// Available parent selection strategies
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
     pub enum ParentSelection {
         RouletteWheel,
         StochasticUniversalSampling,
@@ -39,32 +69,61 @@ arg_enum! {
 // These are wrapped in arg_enum since we are constructing these directly from StructOpt
 arg_enum! {
     /// Available survivor selection strategies
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
     pub enum SurvivorSelection {
         AgeBased,
         FitnessBased,
+        TournamentBased,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available local search modes for memetic evolution
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+    pub enum LocalSearch {
+        None,
+        Lamarckian,
+        Baldwinian,
     }
 }
 
 /// Basic statistics container
-#[derive(Debug, Default)]
-struct EvolutionStats {
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EvolutionStats {
     /// Which generation these stats represent
-    generation: i32,
+    pub generation: i32,
     /// Maximum number of generations in the evolution
-    max_generations: u32,
+    pub max_generations: u32,
     /// Best fitness achieved this generation
-    fitness: f64,
+    pub fitness: f64,
+    /// Best fitness achieved in any generation so far
+    pub best_fitness: Option<f64>,
+    /// The last generation that improved on the best fitness
+    pub last_improvement: i32,
+    /// Mean fitness of the population
+    pub mean: f64,
+    /// Median fitness of the population
+    pub median: f64,
+    /// Worst fitness in the population
+    pub worst: f64,
+    /// Standard deviation of the fitness of the population
+    pub std_dev: f64,
+    /// Fraction of distinct genotypes in the population, only computed when an observer or the
+    /// termination criteria need it
+    pub diversity: f64,
     /// The total elapsed time at this generation
-    elapsed: f32,
+    pub elapsed: f32,
     /// The total number of mutations this generation
-    mutations: i32,
+    pub mutations: i32,
     /// The total number of mutations over the course of evolution
-    total_mutations: i32,
+    pub total_mutations: i32,
     /// The total number of crossovers this generation
-    crossovers: i32,
+    pub crossovers: i32,
     /// The total number of crossovers over the course of evolution
-    total_crossovers: i32,
+    pub total_crossovers: i32,
+    /// The total number of fitness evaluations over the course of evolution
+    pub evaluations: u64,
 }
 
 /// String representation of the statistics container
@@ -91,25 +150,74 @@ impl Display for EvolutionStats {
     }
 }
 
+/// Fraction of distinct genotypes in the population
+fn diversity<T>(population: &[Individual<T>]) -> f64
+where
+    T: Genotype + PartialOrd,
+{
+    // Individuals with different fitness have different genotypes, so it is enough to
+    // compare genotypes within groups of equal fitness
+    let mut order: Vec<&Individual<T>> = population.iter().collect();
+    order.sort_by(|a, b| a.fitness.partial_cmp(&b.fitness).unwrap_or(Ordering::Equal));
+
+    let mut distinct = 0;
+    for (i, individual) in order.iter().enumerate() {
+        let duplicate = order[..i]
+            .iter()
+            .rev()
+            .take_while(|other| other.fitness == individual.fitness)
+            .any(|other| other.genotype == individual.genotype);
+
+        if !duplicate {
+            distinct += 1;
+        }
+    }
+
+    distinct as f64 / population.len() as f64
+}
+
+/// Weight of an individual in fitness proportionate selection
+fn selection_weight<T>(individual: &Individual<T>, minimize: bool) -> f64
+where
+    T: Genotype + PartialOrd,
+{
+    if minimize {
+        1.0 / individual.fitness
+    } else {
+        individual.fitness
+    }
+}
+
+/// Check whether individual a has a better fitness than individual b, or the same fitness and
+/// a smaller genotype
+fn is_better<T>(a: &Individual<T>, b: &Individual<T>, minimize: bool) -> bool
+where
+    T: Genotype + PartialOrd,
+{
+    if a.fitness == b.fitness {
+        a.genotype.size() < b.genotype.size()
+    } else if minimize {
+        a.fitness < b.fitness
+    } else {
+        a.fitness > b.fitness
+    }
+}
+
 /// Select a parent using roulette wheel selection
-fn roulette_wheel_select<'a, T>(
-    population: &'a Vec<Individual<T>>,
+fn roulette_wheel_select<T>(
+    population: &[Individual<T>],
     s: f64,
     minimize: bool,
     rng: &mut impl Rng,
 ) -> usize
 where
-    T: Genotype + Phenotype + Display + PartialOrd,
+    T: Genotype + Display + PartialOrd,
 {
     let mut p = 0.0;
     let t = rng.gen_range(0.0..s);
 
     for (i, individual) in population.iter().enumerate() {
-        if minimize {
-            p += 1.0 / individual.fitness;
-        } else {
-            p += individual.fitness;
-        }
+        p += selection_weight(individual, minimize);
 
         if p >= t {
             return i;
@@ -119,16 +227,106 @@ where
     0
 }
 
+/// Select n parents using stochastic universal sampling, which places n evenly spaced
+/// pointers over the roulette wheel and only spins it once
+fn stochastic_universal_sampling<T>(
+    population: &[Individual<T>],
+    s: f64,
+    minimize: bool,
+    n: usize,
+    rng: &mut impl Rng,
+) -> Vec<usize>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    let mut selected = Vec::with_capacity(n);
+    let distance = s / n as f64;
+    let start = rng.gen_range(0.0..distance);
+    let mut i = 0;
+    let mut p = selection_weight(&population[0], minimize);
+
+    for k in 0..n {
+        let pointer = start + k as f64 * distance;
+
+        while p < pointer && i < population.len() - 1 {
+            i += 1;
+            p += selection_weight(&population[i], minimize);
+        }
+
+        selected.push(i);
+    }
+
+    selected
+}
+
+/// Select a parent by picking the best out of a number of randomly chosen individuals
+fn tournament_select<T>(
+    population: &[Individual<T>],
+    size: usize,
+    minimize: bool,
+    rng: &mut impl Rng,
+) -> usize
+where
+    T: Genotype + Display + PartialOrd,
+{
+    let mut winner = rng.gen_range(0..population.len());
+
+    for _ in 1..size {
+        let challenger = rng.gen_range(0..population.len());
+
+        if is_better(&population[challenger], &population[winner], minimize) {
+            winner = challenger;
+        }
+    }
+
+    winner
+}
+
+/// Select a parent using linear ranking with the given selection pressure in [1.0, 2.0].
+/// Assumes the population is sorted with the best individual first.
+fn rank_select<T>(population: &[Individual<T>], pressure: f64, rng: &mut impl Rng) -> usize
+where
+    T: Genotype + Display + PartialOrd,
+{
+    let n = population.len();
+
+    if n < 2 {
+        return 0;
+    }
+
+    let n = n as f64;
+    let t = rng.gen_range(0.0..1.0);
+    let mut p = 0.0;
+
+    for i in 0..population.len() {
+        // Rank n - 1 is the best individual, rank 0 the worst
+        let rank = n - 1.0 - i as f64;
+        p += (2.0 - pressure) / n + 2.0 * rank * (pressure - 1.0) / (n * (n - 1.0));
+
+        if p >= t {
+            return i;
+        }
+    }
+
+    population.len() - 1
+}
+
 /// Perform mutation on a population with a given mutation rate
-fn mutate<'a, T>(population: &'a mut Vec<Individual<T>>, rate: f64, rng: &mut impl Rng) -> i32
+fn mutate<T>(
+    population: &mut [Individual<T>],
+    rate: f64,
+    context: &T::Context,
+    rng: &mut impl Rng,
+) -> i32
 where
-    T: Genotype + Phenotype + Display + PartialOrd,
+    T: Genotype + Display + PartialOrd,
 {
     let mut count = 0;
 
     for g in population.iter_mut() {
         if rng.gen_bool(rate) {
-            g.genotype.mutate(rng);
+            g.genotype.mutate(context, rng);
+            g.phenotype = None;
             count += 1;
         }
     }
@@ -136,273 +334,1327 @@ where
     count
 }
 
-/// Evaluate a collection of individuals
-fn evaluate<'a, T>(population: &'a mut Vec<Individual<T>>)
+/// Evaluate a single individual, improving it with a local search first if enabled.
+/// Lamarckian search writes the improved genotype back, Baldwinian search only keeps its
+/// fitness, so the cached phenotype still matches the unchanged genotype.
+fn evaluate_individual<T>(
+    individual: &mut Individual<T>,
+    local_search: LocalSearch,
+    context: &T::Context,
+) where
+    T: Genotype + PartialOrd,
+{
+    match local_search {
+        LocalSearch::None => individual.evaluate(context),
+        LocalSearch::Lamarckian => {
+            if let Some(improved) = individual.genotype.improve(context) {
+                individual.genotype = improved;
+            }
+            individual.evaluate(context);
+        }
+        LocalSearch::Baldwinian => match individual.genotype.improve(context) {
+            Some(improved) => {
+                individual.fitness = improved.decode(context).fitness(context);
+                individual.phenotype = Some(individual.genotype.decode(context));
+            }
+            None => individual.evaluate(context),
+        },
+    }
+}
+
+/// Evaluate a collection of individuals, in parallel on the current thread pool if the
+/// parallel feature is enabled. Returns the number of evaluations.
+fn evaluate<T>(
+    population: &mut [Individual<T>],
+    local_search: LocalSearch,
+    context: &T::Context,
+) -> u64
 where
-    T: Genotype + Phenotype + PartialOrd,
+    T: Genotype + PartialOrd,
 {
-    for i in population.into_iter() {
-        i.evaluate();
+    // Evaluation draws no random numbers, so the order does not affect the outcome
+    #[cfg(feature = "parallel")]
+    population
+        .par_iter_mut()
+        .for_each(|i| evaluate_individual(i, local_search, context));
+    #[cfg(not(feature = "parallel"))]
+    population
+        .iter_mut()
+        .for_each(|i| evaluate_individual(i, local_search, context));
+
+    population.len() as u64
+}
+
+/// Runs fitness evaluations on a dedicated thread pool when the parallel feature is enabled.
+/// Clones share the same pool, so islands do not each start a thread per CPU core.
+#[derive(Clone)]
+pub(crate) struct Evaluator {
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl Evaluator {
+    /// Create an evaluator using the given number of threads, 0 means one per CPU core
+    #[cfg(feature = "parallel")]
+    pub(crate) fn new(threads: usize) -> Self {
+        // Fall back to the global pool if a dedicated one cannot be created
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .ok()
+            .map(Arc::new);
+
+        Evaluator { pool }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pub(crate) fn new(_threads: usize) -> Self {
+        Evaluator {}
+    }
+
+    pub(crate) fn evaluate<T>(
+        &self,
+        population: &mut [Individual<T>],
+        local_search: LocalSearch,
+        context: &T::Context,
+    ) -> u64
+    where
+        T: Genotype + PartialOrd,
+    {
+        #[cfg(feature = "parallel")]
+        if let Some(pool) = &self.pool {
+            return pool.install(|| evaluate(population, local_search, context));
+        }
+
+        evaluate(population, local_search, context)
     }
 }
 
-/// Sort a collection of individuals
-fn sort<'a, T>(population: &'a mut Vec<Individual<T>>, reverse: bool)
+impl EvolutionStats {
+    /// Keep the fitness of this generation if it is the best so far, returning whether it
+    /// improved on every earlier generation
+    pub(crate) fn record_improvement(&mut self, minimize: bool) -> bool {
+        let improved = match self.best_fitness {
+            Some(best) if minimize => self.fitness < best,
+            Some(best) => self.fitness > best,
+            None => true,
+        };
+
+        if improved {
+            self.best_fitness = Some(self.fitness);
+            self.last_improvement = self.generation;
+        }
+
+        improved
+    }
+}
+
+/// Tell the observers about a finished generation of the sorted population, and about its best
+/// individual if it improved on all earlier generations. Returns whether any observer wants to
+/// stop.
+pub(crate) fn notify_observers<T>(
+    observers: &mut [Box<dyn Observer<T>>],
+    stats: &EvolutionStats,
+    population: &[Individual<T>],
+    improved: bool,
+) -> Control
 where
-    T: Genotype + Phenotype + PartialOrd,
+    T: Genotype + PartialOrd,
 {
-    if reverse {
-        population.sort_by(|a, b| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    let mut control = Control::Continue;
+
+    for observer in observers.iter_mut() {
+        if improved {
+            observer.on_improvement(stats, &population[0]);
+        }
+        if observer.on_generation(stats, population) == Control::Stop {
+            control = Control::Stop;
+        }
+    }
+
+    control
+}
+
+/// Whether the diversity of the population is needed, which is costly to compute
+pub(crate) fn tracks_diversity<T>(config: &Config, observers: &[Box<dyn Observer<T>>]) -> bool
+where
+    T: Genotype + PartialOrd,
+{
+    config.termination().uses_diversity() || observers.iter().any(|o| o.needs_diversity())
+}
+
+/// Update the fitness statistics of a population in any order, along with its diversity if
+/// asked for
+pub(crate) fn update_fitness_stats<T>(
+    stats: &mut EvolutionStats,
+    population: &[Individual<T>],
+    minimize: bool,
+    with_diversity: bool,
+) where
+    T: Genotype + PartialOrd,
+{
+    let mut fitness: Vec<f64> = population.iter().map(|i| i.fitness).collect();
+    fitness.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    if !minimize {
+        fitness.reverse();
+    }
+
+    let n = fitness.len() as f64;
+    let mean = fitness.iter().sum::<f64>() / n;
+    let variance = fitness.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / n;
+    let middle = fitness.len() / 2;
+
+    stats.fitness = fitness[0];
+    stats.worst = fitness[fitness.len() - 1];
+    stats.mean = mean;
+    stats.std_dev = variance.sqrt();
+    stats.median = if fitness.len().is_multiple_of(2) {
+        (fitness[middle - 1] + fitness[middle]) / 2.0
     } else {
-        population.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        fitness[middle]
+    };
+    if with_diversity {
+        stats.diversity = diversity(population);
     }
 }
 
+/// The population sorted with the best individual first, copying it only when it is not
+fn sorted<T>(
+    population: &[Individual<T>],
+    is_sorted: bool,
+    minimize: bool,
+) -> Cow<'_, [Individual<T>]>
+where
+    T: Genotype + PartialOrd,
+{
+    if is_sorted {
+        Cow::Borrowed(population)
+    } else {
+        let mut copy = population.to_vec();
+        sort(&mut copy, !minimize);
+        Cow::Owned(copy)
+    }
+}
+
+/// Select indices of n parents from the given population using the configured strategy
+fn select_indices<T>(
+    population: &[Individual<T>],
+    n: usize,
+    total_fitness: f64,
+    config: &Config,
+    rng: &mut impl Rng,
+) -> Vec<usize>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    let minimize = config.minimize;
+
+    match config.parent_selection {
+        ParentSelection::RouletteWheel => (0..n)
+            .map(|_| roulette_wheel_select(population, total_fitness, minimize, rng))
+            .collect(),
+        ParentSelection::StochasticUniversalSampling => {
+            let mut pool =
+                stochastic_universal_sampling(population, total_fitness, minimize, n, rng);
+            // Pointers are evenly spaced, so shuffle to avoid always mating neighbours
+            pool.shuffle(rng);
+            pool
+        }
+        ParentSelection::TournamentSelection => (0..n)
+            .map(|_| tournament_select(population, config.tournament_size, minimize, rng))
+            .collect(),
+        ParentSelection::RankSelection => (0..n)
+            .map(|_| rank_select(population, config.selection_pressure, rng))
+            .collect(),
+    }
+}
+
+/// Produce one offspring for every pair of parent indices in the pool through crossover and
+/// mutation, counting both in the statistics
+pub(crate) fn breed<T>(
+    parents: &[Individual<T>],
+    pool: &[usize],
+    config: &Config,
+    context: &T::Context,
+    rng: &mut impl Rng,
+    stats: &mut EvolutionStats,
+) -> Vec<Individual<T>>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    let mut offspring: Vec<Individual<T>> = Vec::with_capacity(pool.len() / 2);
+
+    for pair in pool.chunks(2) {
+        let individual_a = &parents[pair[0]];
+        let new = if rng.gen_bool(config.crossover_rate) {
+            stats.crossovers += 1;
+            let individual_b = &parents[pair[1]];
+            individual_a.crossover(individual_b, stats.generation, context, rng)
+        } else {
+            Individual {
+                generation: stats.generation,
+                ..individual_a.clone()
+            }
+        };
+
+        offspring.push(new);
+    }
+
+    // Mutate offspring
+    stats.mutations += mutate(&mut offspring, config.mutation_rate, context, rng);
+
+    offspring
+}
+
+/// Order of two individuals when sorting
+fn compare<T>(a: &Individual<T>, b: &Individual<T>, reverse: bool) -> Ordering
+where
+    T: Genotype + PartialOrd,
+{
+    let order = if reverse {
+        b.partial_cmp(a)
+    } else {
+        a.partial_cmp(b)
+    };
+
+    // Lexicographic parsimony: of two equally fit individuals the smaller one comes first
+    if a.fitness == b.fitness {
+        a.genotype
+            .size()
+            .cmp(&b.genotype.size())
+            .then(order.unwrap_or(Ordering::Equal))
+    } else {
+        order.unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Sort a collection of individuals
+pub(crate) fn sort<T>(population: &mut [Individual<T>], reverse: bool)
+where
+    T: Genotype + PartialOrd,
+{
+    population.sort_by(|a, b| compare(a, b, reverse));
+}
+
+/// Settings, context, random number generator, individuals and observers of a population
+pub(crate) type PopulationParts<T> = (
+    Config,
+    Arc<<T as Genotype>::Context>,
+    EvolutionRng,
+    Vec<Individual<T>>,
+    Vec<Box<dyn Observer<T>>>,
+);
+
 /// Simple sandbox population
-#[derive(Debug)]
 pub struct StandardPopulation<T>
 where
-    T: Genotype + Phenotype + Display + PartialOrd,
+    T: Genotype + Display + PartialOrd,
 {
-    options: Options,
-    rng: rand::rngs::ThreadRng,
+    config: Config,
+    context: Arc<T::Context>,
+    rng: EvolutionRng,
     stats: EvolutionStats,
     population: Vec<Individual<T>>,
+    evaluator: Evaluator,
+    /// Cells in the neighbourhood of every cell of a cellular population
+    neighbourhoods: Vec<Vec<usize>>,
     started: SystemTime,
-    last_print: f32,
+    /// Time spent evolving before this population was restored from a checkpoint
+    resumed_elapsed: Duration,
+    /// Whether the population has been evaluated, which a restored population already is
+    evaluated: bool,
+    /// File to save checkpoints to and the number of generations between them
+    checkpoint: Option<(PathBuf, u32)>,
+    /// Set from outside, for example by a signal handler, to save a checkpoint and stop
+    interrupt: Option<Arc<AtomicBool>>,
+    observers: Vec<Box<dyn Observer<T>>>,
 }
 
 /// Standard population implementation
 impl<T> StandardPopulation<T>
 where
-    T: Genotype + Phenotype + Display + PartialOrd,
+    T: Genotype + Display + PartialOrd,
 {
-    /// Select parents for crossover and mutation
-    fn select_parents(&mut self, total_fitness: f64) -> Vec<Individual<T>> {
-        let mut new_population: Vec<Individual<T>> = Vec::with_capacity(self.options.problem_size);
-
-        // TODO: Optimize, move chosen selector to struct member
-        match self.options.parent_selection {
-            ParentSelection::RouletteWheel => {
-                while new_population.len() < self.options.population as usize {
-                    let a = roulette_wheel_select(
-                        &self.population,
-                        total_fitness,
-                        self.options.minimize,
-                        &mut self.rng,
-                    );
-                    let individual_a = &self.population[a];
-                    let new: Individual<T>;
-
-                    if self.rng.gen_bool(self.options.crossover_rate) {
-                        self.stats.crossovers += 1;
-                        let b = roulette_wheel_select(
-                            &self.population,
-                            total_fitness,
-                            self.options.minimize,
-                            &mut self.rng,
-                        );
-                        let individual_b = &self.population[b];
-                        new = individual_a.crossover(
-                            individual_b,
-                            self.stats.generation,
-                            &mut self.rng,
-                        );
-                    } else {
-                        // TODO: Clean this up. Need to move or copy
-                        new = individual_a.crossover(
-                            individual_a,
-                            self.stats.generation,
-                            &mut self.rng,
-                        );
-                    }
+    /// Select parents and produce n offspring through crossover and mutation
+    fn select_parents(&mut self, n: usize, total_fitness: f64) -> Vec<Individual<T>> {
+        // Draw two parents per offspring, the second one is only used for crossover
+        let pool = select_indices(
+            &self.population,
+            2 * n,
+            total_fitness,
+            &self.config,
+            &mut self.rng,
+        );
 
-                    new_population.push(new);
-                }
-            }
-            _ => {
-                // TODO: Implement support for more methods
-            }
+        breed(
+            &self.population,
+            &pool,
+            &self.config,
+            &self.context,
+            &mut self.rng,
+            &mut self.stats,
+        )
+    }
+
+    /// Whether the population is kept sorted with the best individual first, which is not
+    /// the case for a cellular population where the order is the layout of the grid
+    fn is_sorted(&self) -> bool {
+        !matches!(self.config.population_model, PopulationModel::Cellular)
+    }
+
+    /// Index of the best individual
+    fn best_index(&self) -> usize {
+        if self.is_sorted() {
+            return 0;
         }
 
-        // Mutate offspring
-        self.stats.mutations = mutate(
-            &mut new_population,
-            self.options.mutation_rate,
+        let minimize = self.config.minimize;
+        (1..self.population.len()).fold(0, |best, i| {
+            if is_better(&self.population[i], &self.population[best], minimize) {
+                i
+            } else {
+                best
+            }
+        })
+    }
+
+    /// Produce an offspring for a cell of a cellular population from parents selected in
+    /// its neighbourhood
+    fn breed_cell(&mut self, cell: usize) -> Individual<T> {
+        let mut neighbours: Vec<Individual<T>> = self.neighbourhoods[cell]
+            .iter()
+            .map(|&i| self.population[i].clone())
+            .collect();
+        // Rank selection relies on the candidates being sorted
+        sort(&mut neighbours, !self.config.minimize);
+
+        let total_fitness = neighbours
+            .iter()
+            .map(|i| selection_weight(i, self.config.minimize))
+            .sum();
+        let pool = select_indices(&neighbours, 2, total_fitness, &self.config, &mut self.rng);
+
+        let mut offspring = breed(
+            &neighbours,
+            &pool,
+            &self.config,
+            &self.context,
             &mut self.rng,
+            &mut self.stats,
         );
+        offspring.pop().unwrap()
+    }
+
+    /// Put an evaluated offspring in its cell if it is at least as good as the current
+    /// individual. With age based survivor selection offspring always replace the current
+    /// individual, unless elitism protects it as the best individual.
+    fn replace_cell(&mut self, cell: usize, offspring: Individual<T>, best: usize) {
+        let current = &self.population[cell];
+        let worse = is_better(current, &offspring, self.config.minimize);
+        let replace = match self.config.survivor_selection {
+            SurvivorSelection::AgeBased => !(worse && self.config.elitism() && cell == best),
+            // Config::validate rejects tournaments in cellular populations
+            SurvivorSelection::FitnessBased | SurvivorSelection::TournamentBased => !worse,
+        };
 
-        // If we have elitism, replace one individual with the best from the existing population
-        if !self.options.no_elitism {
-            new_population.pop();
-            new_population.push(self.population.remove(0));
+        if replace {
+            self.population[cell] = offspring;
         }
+    }
+
+    /// Advance a cellular population by breeding an offspring for every cell, either all
+    /// from the current grid or one cell at a time in a random order
+    fn cellular_step(&mut self) {
+        let cells = self.population.len();
+
+        if self.neighbourhoods.len() != cells {
+            self.neighbourhoods = neighbourhoods(
+                cells,
+                self.config.grid_width,
+                self.config.neighbourhood,
+                self.config.neighbourhood_radius,
+            );
+        }
+
+        let best = self.best_index();
+
+        match self.config.cellular_update {
+            CellularUpdate::Synchronous => {
+                let mut offspring: Vec<Individual<T>> =
+                    (0..cells).map(|cell| self.breed_cell(cell)).collect();
+                self.stats.evaluations += self.evaluator.evaluate(
+                    &mut offspring,
+                    self.config.local_search,
+                    &self.context,
+                );
+
+                for (cell, individual) in offspring.into_iter().enumerate() {
+                    self.replace_cell(cell, individual, best);
+                }
+            }
+            CellularUpdate::Asynchronous => {
+                let mut order: Vec<usize> = (0..cells).collect();
+                order.shuffle(&mut self.rng);
+
+                for cell in order {
+                    let mut offspring = vec![self.breed_cell(cell)];
+                    self.stats.evaluations += self.evaluator.evaluate(
+                        &mut offspring,
+                        self.config.local_search,
+                        &self.context,
+                    );
+                    self.replace_cell(cell, offspring.pop().unwrap(), best);
+                }
+            }
+        }
+    }
+
+    /// Number of offspring to produce each generation
+    fn offspring_count(&self) -> usize {
+        match self.config.population_model {
+            PopulationModel::SteadyState => self
+                .config
+                .replacement_count
+                .min(self.config.population)
+                .max(1),
+            PopulationModel::Generational | PopulationModel::Cellular => self.config.population,
+            PopulationModel::MuPlusLambda | PopulationModel::MuCommaLambda => {
+                self.config.offspring.unwrap_or(self.config.population)
+            }
+        }
+    }
+
+    /// Pick the indices of the individuals that are replaced by offspring in a steady state
+    /// population. The best individual is protected when elitism is on.
+    fn select_replacements(&mut self, n: usize) -> Vec<usize> {
+        let protected = if self.config.elitism() { 1 } else { 0 };
+        let len = self.population.len();
+        let n = n.min(len - protected);
+
+        match self.config.survivor_selection {
+            SurvivorSelection::FitnessBased => {
+                // Population is sorted with the best individual first, so the worst are at the end
+                (len - n..len).collect()
+            }
+            SurvivorSelection::AgeBased => {
+                let mut candidates: Vec<usize> = (protected..len).collect();
+                // Oldest first, ties are broken by replacing the least fit individual
+                candidates.sort_by(|a, b| {
+                    self.population[*a]
+                        .generation
+                        .cmp(&self.population[*b].generation)
+                        .then(b.cmp(a))
+                });
+                candidates.truncate(n);
+                candidates
+            }
+            SurvivorSelection::TournamentBased => {
+                // Every tournament is drawn from the individuals not yet replaced, so each
+                // picks a new loser
+                let mut candidates: Vec<usize> = (protected..len).collect();
+                let mut losers: Vec<usize> = Vec::with_capacity(n);
+
+                for _ in 0..n {
+                    let mut loser = self.rng.gen_range(0..candidates.len());
+
+                    for _ in 1..self.config.tournament_size {
+                        let challenger = self.rng.gen_range(0..candidates.len());
+
+                        if is_better(
+                            &self.population[candidates[loser]],
+                            &self.population[candidates[challenger]],
+                            self.config.minimize,
+                        ) {
+                            loser = challenger;
+                        }
+                    }
+
+                    losers.push(candidates.swap_remove(loser));
+                }
 
-        new_population
+                losers
+            }
+        }
     }
 
     /// Select survivors of this generation
-    fn select_survivors(&mut self, new_generation: Vec<Individual<T>>) {
+    fn select_survivors(&mut self, mut offspring: Vec<Individual<T>>) {
         // Population model determines if we are replacing entire generation or
         // performing some sort of generational mixing
-        match self.options.population_model {
+        match self.config.population_model {
             PopulationModel::SteadyState => {
-                match self.options.survivor_selection {
-                    SurvivorSelection::FitnessBased => {
-                        // TODO: Implement support for one of the fitness based selectors like
-                        // roulette wheel or tournament etc
-                    }
-                    SurvivorSelection::AgeBased => {
-                        // TODO: Implement support
-                    }
+                let replacements = self.select_replacements(offspring.len());
+
+                for (index, individual) in replacements.into_iter().zip(offspring) {
+                    self.population[index] = individual;
                 }
             }
             PopulationModel::Generational => {
-                self.population = new_generation;
+                // If we have elitism, replace the worst offspring with the best from the
+                // existing population
+                if self.config.elitism() {
+                    offspring.pop();
+                    offspring.push(self.population.remove(0));
+                }
+
+                self.population = offspring;
+            }
+            PopulationModel::MuPlusLambda => {
+                // (μ+λ): the best μ of parents and offspring survive
+                self.population.append(&mut offspring);
+                sort(&mut self.population, !self.config.minimize);
+                self.population.truncate(self.config.population);
+            }
+            PopulationModel::MuCommaLambda => {
+                // (μ,λ): the best μ offspring survive, keeping the best parent only if elitism
+                // was asked for
+                offspring.truncate(self.config.population);
+
+                if self.config.elitism() {
+                    offspring.pop();
+                    offspring.push(self.population.remove(0));
+                }
+
+                self.population = offspring;
+            }
+            PopulationModel::Cellular => {
+                unreachable!("cellular populations replace cells in place")
             }
         }
+
+        sort(&mut self.population, !self.config.minimize);
     }
 
-    /// Advance to the next generation
-    fn next(&mut self) {
+    /// Advance to the next generation, returning whether any observer wants to stop
+    pub(crate) fn next(&mut self) -> Control {
         self.stats.generation += 1;
         self.stats.mutations = 0;
         self.stats.crossovers = 0;
 
-        let mut total_fitness = 0.0;
-        for i in &self.population {
-            if self.options.minimize {
-                total_fitness += 1.0 / i.fitness;
-            } else {
-                total_fitness += i.fitness
-            }
-        }
+        let total_fitness = self
+            .population
+            .iter()
+            .map(|i| selection_weight(i, self.config.minimize))
+            .sum();
 
-        let mut new_generation = self.select_parents(total_fitness);
+        if self.is_sorted() {
+            let mut offspring = self.select_parents(self.offspring_count(), total_fitness);
 
-        evaluate(&mut new_generation);
-        sort(&mut new_generation, !self.options.minimize);
+            self.stats.evaluations +=
+                self.evaluator
+                    .evaluate(&mut offspring, self.config.local_search, &self.context);
+            sort(&mut offspring, !self.config.minimize);
 
-        self.select_survivors(new_generation);
-        let best = &self.population[0];
+            self.select_survivors(offspring);
+        } else {
+            self.cellular_step();
+        }
 
-        self.stats.fitness = best.fitness;
+        self.update_stats();
         self.stats.total_mutations += self.stats.mutations;
         self.stats.total_crossovers += self.stats.crossovers;
-        self.stats.elapsed = self.started.elapsed().unwrap().as_secs_f32();
-
-        // Output status every second
-        if self.stats.elapsed - self.last_print > 1.0 {
-            println!("{} Best: {}", self.stats, best);
-            if self.options.debug {
-                println!(
-                    "{:?}",
-                    self.population
-                        .iter()
-                        .map(|i| i.fitness)
-                        .collect::<Vec<f64>>()
-                );
-                println!("{}", best.genotype);
-            }
-            self.last_print = self.stats.elapsed;
-        }
+        self.stats.elapsed = self.elapsed().as_secs_f32();
+
+        let improved = self.stats.record_improvement(self.config.minimize);
+        let population = sorted(&self.population, self.is_sorted(), self.config.minimize);
+
+        notify_observers(&mut self.observers, &self.stats, &population, improved)
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<Individual<T>> {
-        self.population.iter_mut()
+    /// Update the fitness statistics of the sorted population
+    fn update_stats(&mut self) {
+        let with_diversity = tracks_diversity(&self.config, &self.observers);
+        update_fitness_stats(
+            &mut self.stats,
+            &self.population,
+            self.config.minimize,
+            with_diversity,
+        );
     }
-}
 
-/// Implementation of the Population trait for the simple sandbox population
-impl<T> Population for StandardPopulation<T>
-where
-    T: Genotype + Phenotype + Display + PartialOrd,
-{
-    /// Evolve this population based on the given command line arguments
-    fn evolve(&mut self) {
-        self.started = SystemTime::now();
+    /// Total time spent evolving, including time before a resumed checkpoint
+    fn elapsed(&self) -> Duration {
+        self.resumed_elapsed + self.started.elapsed().unwrap()
+    }
+
+    /// Whether the interrupt flag has been raised
+    fn interrupted(&self) -> bool {
+        match &self.interrupt {
+            Some(flag) => flag.load(AtomicOrdering::SeqCst),
+            None => false,
+        }
+    }
 
-        // Calculate fitness and sort the new population
-        evaluate(&mut self.population);
-        sort(&mut self.population, !self.options.minimize);
+    /// Save a checkpoint if one is due this generation
+    fn save_due_checkpoint(&self) {
+        if let Some((path, interval)) = &self.checkpoint {
+            if *interval > 0 && self.stats.generation % *interval as i32 == 0 {
+                self.report_checkpoint(path);
+            }
+        }
+    }
 
-        if self.options.debug {
-            println!("{:?}", self.options);
+    /// Save a checkpoint, reporting rather than failing since the evolution itself is fine
+    fn report_checkpoint(&self, path: &Path) {
+        if let Err(e) = self.save_checkpoint(path) {
+            eprintln!("Failed to save checkpoint {}: {}", path.display(), e);
         }
+    }
 
-        if self.options.max_generations == 0 {
-            println!(
-                "Attempting to evolve {} ({}) until target fitness {:.3} is met",
-                self.options.problem, self.options.problem_size, self.options.target_fitness
-            );
-        } else {
-            println!(
-                "Attempting to evolve {} ({}) to target fitness {:.3} in maximum {} generations",
-                self.options.problem,
-                self.options.problem_size,
-                self.options.target_fitness,
-                self.options.max_generations
+    /// Calculate the fitness of and sort the initial population, unless it was restored
+    pub(crate) fn initialize(&mut self) {
+        if !self.evaluated {
+            self.stats.evaluations += self.evaluator.evaluate(
+                &mut self.population,
+                self.config.local_search,
+                &self.context,
             );
+            if self.is_sorted() {
+                sort(&mut self.population, !self.config.minimize);
+            }
+            self.update_stats();
+            self.stats.record_improvement(self.config.minimize);
+            self.evaluated = true;
         }
+    }
 
-        // Max generations of 0 means run until target fitness is met
-        if self.options.max_generations == 0 {
-            loop {
-                self.next();
+    /// Indices of the individuals with the best first, in the same order as sort
+    fn ranked_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.population.len()).collect();
 
-                if (self.options.minimize && self.stats.fitness <= self.options.target_fitness)
-                    || (!self.options.minimize && self.stats.fitness >= self.options.target_fitness)
-                {
-                    break;
-                }
-            }
+        if !self.is_sorted() {
+            let population = &self.population;
+            let reverse = !self.config.minimize;
+            indices.sort_by(|a, b| compare(&population[*a], &population[*b], reverse));
+        }
+
+        indices
+    }
+
+    /// Copies of the n best individuals, the best first
+    pub(crate) fn fittest(&self, n: usize) -> Vec<Individual<T>> {
+        self.ranked_indices()
+            .into_iter()
+            .take(n)
+            .map(|i| self.population[i].clone())
+            .collect()
+    }
+
+    /// Replace the worst individuals with the given migrants, always keeping the best one
+    pub(crate) fn immigrate(&mut self, migrants: Vec<Individual<T>>) {
+        let n = migrants.len().min(self.population.len() - 1);
+
+        if self.is_sorted() {
+            self.population.truncate(self.population.len() - n);
+            self.population.extend(migrants.into_iter().take(n));
+            sort(&mut self.population, !self.config.minimize);
         } else {
-            for _ in 0..self.options.max_generations {
-                self.next();
+            // Migrants take over the cells of the worst individuals
+            let mut cells = self.ranked_indices();
+            cells.drain(..cells.len() - n);
 
-                if (self.options.minimize && self.stats.fitness <= self.options.target_fitness)
-                    || (!self.options.minimize && self.stats.fitness >= self.options.target_fitness)
-                {
-                    break;
-                }
+            for (cell, migrant) in cells.into_iter().zip(migrants) {
+                self.population[cell] = migrant;
             }
         }
+        self.update_stats();
+    }
 
-        let best = &self.population[0];
-        println!(
-            "Reached {:.3} fitness in {} generations after {:.3}s with {} mutations and {} crossovers",
-            self.stats.fitness,
-            self.stats.generation,
-            self.started.elapsed().unwrap().as_secs_f32(),
-            self.stats.total_mutations,
-            self.stats.total_crossovers
-        );
-        println!("{}", &best.genotype);
+    /// Split into the given number of islands sharing the context of the problem. This
+    /// population becomes the first island, the others and the returned generator for
+    /// coordinating them are seeded from it. The observers are returned rather than moved to
+    /// any island.
+    pub(crate) fn into_islands(
+        mut self,
+        count: usize,
+    ) -> (Vec<Self>, EvolutionRng, Vec<Box<dyn Observer<T>>>) {
+        let observers = std::mem::take(&mut self.observers);
+        let rng = EvolutionRng::seed_from_u64(self.rng.gen());
+        let mut islands = Vec::with_capacity(count);
+
+        for _ in 1..count {
+            let rng = EvolutionRng::seed_from_u64(self.rng.gen());
+            islands.push(Self::with_shared_context(
+                self.config.clone(),
+                self.context.clone(),
+                self.evaluator.clone(),
+                rng,
+            ));
+        }
+        islands.insert(0, self);
+
+        (islands, rng, observers)
     }
 
-    /// Create a new standard population
-    fn new(options: Options) -> Self {
-        let mut rng = thread_rng();
-        let mut population: Vec<Individual<T>> = Vec::with_capacity(options.population);
+    /// Take the population apart into its settings, context, random number generator, initial
+    /// individuals and observers, so another population model can take over
+    pub(crate) fn into_parts(self) -> PopulationParts<T> {
+        (
+            self.config,
+            self.context,
+            self.rng,
+            self.population,
+            self.observers,
+        )
+    }
+
+    /// Create a new standard population drawing all randomness from the given generator
+    pub fn with_rng(config: Config, context: T::Context, rng: EvolutionRng) -> Self {
+        let evaluator = Evaluator::new(config.threads);
+        Self::with_shared_context(config, Arc::new(context), evaluator, rng)
+    }
+
+    /// Create a new standard population for a context and evaluator shared with other
+    /// populations
+    pub(crate) fn with_shared_context(
+        config: Config,
+        context: Arc<T::Context>,
+        evaluator: Evaluator,
+        mut rng: EvolutionRng,
+    ) -> Self {
+        let mut population: Vec<Individual<T>> = Vec::with_capacity(config.population);
 
-        for _ in 0..options.population {
-            population.push(Individual {
-                generation: 0,
-                fitness: 0.0,
-                genotype: T::new(&mut rng, &options),
-            });
+        for _ in 0..config.population {
+            population.push(Individual::new(T::new(&mut rng, &context), 0));
         }
 
         StandardPopulation {
             population,
             stats: EvolutionStats {
-                max_generations: options.max_generations,
+                max_generations: config.max_generations,
                 ..Default::default()
             },
             rng,
-            options,
+            evaluator,
+            neighbourhoods: Vec::new(),
+            config,
+            context,
             started: SystemTime::now(),
-            last_print: 0.0,
+            resumed_elapsed: Duration::from_secs(0),
+            evaluated: false,
+            checkpoint: None,
+            interrupt: None,
+            observers: Vec::new(),
         }
     }
+
+    /// Save the settings, random number generator state, individuals and statistics so the
+    /// evolution can be resumed later
+    pub fn save_checkpoint(&self, path: &Path) -> io::Result<()> {
+        Checkpoint {
+            config: self.config.clone(),
+            rng: self.rng.clone(),
+            population: self.population.clone(),
+            stats: self.stats.clone(),
+            elapsed: self.elapsed(),
+        }
+        .save(path)
+    }
+
+    /// Restore the state saved in a checkpoint, including its settings. The context of the
+    /// problem is not part of a checkpoint and must be the same as when it was saved.
+    pub fn load_checkpoint(&mut self, path: &Path) -> io::Result<()> {
+        let checkpoint = Checkpoint::load(path)?;
+
+        // The number of threads depends on the machine rather than the evolution
+        self.config = Config {
+            threads: self.config.threads,
+            ..checkpoint.config
+        };
+        self.rng = checkpoint.rng;
+        self.population = checkpoint.population;
+        self.stats = checkpoint.stats;
+        self.resumed_elapsed = checkpoint.elapsed;
+        self.evaluated = true;
+
+        // Phenotypes are not saved, but the fitness is, so they only need decoding
+        for individual in self.population.iter_mut() {
+            individual.decode(&self.context);
+        }
+
+        Ok(())
+    }
+
+    /// Save a checkpoint to the given file every interval generations, 0 only saves when
+    /// interrupted
+    pub fn set_checkpoint(&mut self, path: PathBuf, interval: u32) {
+        self.checkpoint = Some((path, interval));
+    }
+
+    /// Stop the evolution once the flag is set, saving a checkpoint first if enabled
+    pub fn set_interrupt(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.observers.push(observer);
+    }
+
+    /// The best individual in the population
+    pub fn best(&self) -> &Individual<T> {
+        &self.population[self.best_index()]
+    }
+
+    /// The individuals of the population, sorted with the best first once evaluated, except
+    /// for a cellular population where they are in the order of the grid
+    pub fn individuals(&self) -> &[Individual<T>] {
+        &self.population
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn context(&self) -> &T::Context {
+        &self.context
+    }
+
+    /// Statistics of the current generation
+    pub fn stats(&self) -> &EvolutionStats {
+        &self.stats
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, Individual<T>> {
+        self.population.iter_mut()
+    }
+}
+
+/// Implementation of the Population trait for the simple sandbox population
+impl<T> Population for StandardPopulation<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    type Genotype = T;
+
+    /// Evolve this population based on its settings
+    fn evolve(&mut self) -> RunResult<T> {
+        self.started = SystemTime::now();
+        self.initialize();
+
+        let population = sorted(&self.population, self.is_sorted(), self.config.minimize);
+        for observer in self.observers.iter_mut() {
+            observer.on_start(&self.config, &population);
+        }
+
+        let condition = self.config.termination();
+        let termination = loop {
+            if let Some(reason) = condition.check(&self.stats, self.config.minimize) {
+                break reason;
+            }
+            if self.interrupted() {
+                if let Some((path, _)) = &self.checkpoint {
+                    self.report_checkpoint(path);
+                }
+                break TerminationReason::Interrupted;
+            }
+
+            if self.next() == Control::Stop {
+                break TerminationReason::Stopped;
+            }
+            self.save_due_checkpoint();
+        };
+
+        let result = RunResult {
+            best: self.best().clone(),
+            stats: self.stats.clone(),
+            generations: self.stats.generation,
+            elapsed: self.elapsed(),
+            evaluations: self.stats.evaluations,
+            termination,
+            pareto_front: Vec::new(),
+            hypervolume: None,
+        };
+
+        for observer in self.observers.iter_mut() {
+            observer.on_finish(&result);
+        }
+
+        result
+    }
+
+    /// Create a new standard population
+    fn new(config: Config, context: T::Context) -> Self {
+        let rng = create_rng(config.seed);
+        Self::with_rng(config, context, rng)
+    }
+}
+
+#[test]
+fn test_seeded_runs_are_identical() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let mut a = GeneticAlgorithm::<NQueens>::builder().seed(42).build(12);
+    let mut b = GeneticAlgorithm::<NQueens>::builder().seed(42).build(12);
+
+    evaluate(&mut a.population, LocalSearch::None, &12);
+    evaluate(&mut b.population, LocalSearch::None, &12);
+    for _ in 0..50 {
+        a.next();
+        b.next();
+    }
+
+    assert_eq!(a.population, b.population);
+    assert_eq!(a.stats.total_mutations, b.stats.total_mutations);
+    assert_eq!(a.stats.total_crossovers, b.stats.total_crossovers);
+}
+
+#[test]
+fn test_steady_state_replacements() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    for selection in &[
+        SurvivorSelection::FitnessBased,
+        SurvivorSelection::AgeBased,
+        SurvivorSelection::TournamentBased,
+    ] {
+        let mut ga = GeneticAlgorithm::<NQueens>::builder()
+            .seed(3)
+            .population(100)
+            .population_model(PopulationModel::SteadyState)
+            .replacement_count(100)
+            .survivor_selection(*selection)
+            .tournament_size(5)
+            .build(12);
+        for (i, individual) in ga.population.iter_mut().enumerate() {
+            individual.fitness = (100 - i) as f64;
+            individual.generation = (i / 10) as i32;
+        }
+
+        // Everyone but the protected best is replaced exactly once
+        let mut replaced = ga.select_replacements(100);
+        replaced.sort_unstable();
+        assert_eq!(replaced, (1..100).collect::<Vec<usize>>());
+
+        let replaced = ga.select_replacements(10);
+        match selection {
+            SurvivorSelection::FitnessBased => {
+                assert_eq!(replaced, (90..100).collect::<Vec<usize>>())
+            }
+            SurvivorSelection::AgeBased => {
+                // The nine oldest after the protected best, then the least fit of the next age
+                let mut expected: Vec<usize> = (1..10).rev().collect();
+                expected.push(19);
+                assert_eq!(replaced, expected)
+            }
+            SurvivorSelection::TournamentBased => {
+                assert!(replaced.iter().sum::<usize>() > 10 * 50)
+            }
+        }
+
+        evaluate(&mut ga.population, LocalSearch::None, &12);
+        for _ in 0..5 {
+            ga.next();
+        }
+        assert_eq!(ga.population.len(), 100);
+    }
+}
+
+#[test]
+fn test_comma_selection_can_lose_the_best() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let best_fitness = |model: PopulationModel| {
+        let mut ga = GeneticAlgorithm::<NQueens>::builder()
+            .seed(4)
+            .population(10)
+            .population_model(model)
+            .mutation_rate(1.0)
+            .build(12);
+        evaluate(&mut ga.population, LocalSearch::None, &12);
+
+        let mut best = vec![ga.population[0].fitness];
+        for _ in 0..30 {
+            ga.next();
+            best.push(ga.population[0].fitness);
+        }
+        best
+    };
+
+    let plus = best_fitness(PopulationModel::MuPlusLambda);
+    assert!(plus.windows(2).all(|w| w[1] >= w[0]));
+    let comma = best_fitness(PopulationModel::MuCommaLambda);
+    assert!(comma.windows(2).any(|w| w[1] < w[0]));
+
+    let too_few = GeneticAlgorithm::<NQueens>::builder()
+        .population(10)
+        .offspring(5)
+        .population_model(PopulationModel::MuCommaLambda)
+        .try_build(12);
+    assert!(too_few.is_err());
+}
+
+#[cfg(test)]
+struct StopAfter(i32);
+
+#[cfg(test)]
+impl Observer<crate::problems::NQueens> for StopAfter {
+    fn on_generation(
+        &mut self,
+        stats: &EvolutionStats,
+        _: &[Individual<crate::problems::NQueens>],
+    ) -> Control {
+        if stats.generation >= self.0 {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+}
+
+#[test]
+fn test_selection_bias() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let mut population = GeneticAlgorithm::<NQueens>::builder()
+        .population(5)
+        .build(8)
+        .population;
+    for (individual, fitness) in population.iter_mut().zip(&[5.0, 4.0, 3.0, 2.0, 1.0]) {
+        individual.fitness = *fitness;
+    }
+    let mut rng = create_rng(Some(1));
+    let count = |selected: Vec<usize>| {
+        let mut counts = [0; 5];
+        for i in selected {
+            counts[i] += 1;
+        }
+        counts
+    };
+
+    // One pointer per unit of fitness selects every individual exactly in proportion
+    let selected = stochastic_universal_sampling(&population, 15.0, false, 15, &mut rng);
+    assert_eq!(count(selected), [5, 4, 3, 2, 1]);
+
+    // Binary tournaments pick the best with probability 1 - (4/5)², the worst with 1/25
+    let counts = count(
+        (0..10000)
+            .map(|_| tournament_select(&population, 2, false, &mut rng))
+            .collect(),
+    );
+    assert!((3400..3800).contains(&counts[0]), "{:?}", counts);
+    assert!((250..550).contains(&counts[4]), "{:?}", counts);
+
+    // Maximum pressure gives the best individual twice the average chance and the worst none
+    let counts = count(
+        (0..10000)
+            .map(|_| rank_select(&population, 2.0, &mut rng))
+            .collect(),
+    );
+    assert!((3800..4200).contains(&counts[0]), "{:?}", counts);
+    assert_eq!(counts[4], 0);
+
+    let pressure = GeneticAlgorithm::<NQueens>::builder()
+        .selection_pressure(2.5)
+        .try_build(8);
+    assert!(pressure.is_err());
+}
+
+#[test]
+fn test_builder_rejects_invalid_settings() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let builder = GeneticAlgorithm::<NQueens>::builder;
+    assert!(builder().population(0).try_build(8).is_err());
+    assert!(builder().mutation_rate(1.5).try_build(8).is_err());
+    assert!(builder().crossover_rate(-0.1).try_build(8).is_err());
+    assert!(builder().crossover_rate(1.0).try_build(8).is_ok());
+    assert!(builder()
+        .population_model(PopulationModel::Cellular)
+        .survivor_selection(SurvivorSelection::TournamentBased)
+        .try_build(8)
+        .is_err());
+}
+
+#[test]
+fn test_observer_stops_evolution() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let result = GeneticAlgorithm::<NQueens>::builder()
+        .seed(1)
+        .target_fitness(2.0)
+        .observer(StopAfter(5))
+        .build(16)
+        .evolve();
+
+    assert_eq!(result.termination, TerminationReason::Stopped);
+    assert_eq!(result.generations, 5);
+}
+
+#[test]
+fn test_resumed_run_is_identical() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let path = std::env::temp_dir().join(format!("rust-ga-{}.checkpoint", std::process::id()));
+    let builder = || {
+        GeneticAlgorithm::<NQueens>::builder()
+            .seed(7)
+            .target_fitness(2.0)
+            .max_generations(40)
+    };
+
+    let mut uninterrupted = builder().build(12);
+    let expected = uninterrupted.evolve();
+
+    let mut interrupted = builder().observer(StopAfter(15)).build(12);
+    interrupted.evolve();
+    interrupted.save_checkpoint(&path).unwrap();
+
+    let mut resumed = builder().build(12);
+    resumed.load_checkpoint(&path).unwrap();
+    let result = resumed.evolve();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(result.termination, TerminationReason::MaxGenerations);
+    assert_eq!(result.evaluations, expected.evaluations);
+    assert_eq!(resumed.population, uninterrupted.population);
+    assert_eq!(resumed.rng, uninterrupted.rng);
+}
+
+#[test]
+fn test_thread_count_does_not_change_results() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let run = |threads| {
+        GeneticAlgorithm::<NQueens>::builder()
+            .seed(3)
+            .threads(threads)
+            .target_fitness(2.0)
+            .max_generations(30)
+            .build(12)
+            .evolve()
+    };
+    let a = run(1);
+    let b = run(4);
+
+    assert_eq!(a.best, b.best);
+    assert_eq!(a.stats.mean, b.stats.mean);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_islands_share_thread_pool() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let (islands, _, _) = GeneticAlgorithm::<NQueens>::builder()
+        .threads(2)
+        .build(8)
+        .into_islands(3);
+    let pool = islands[0].evaluator.pool.as_ref().unwrap();
+
+    assert!(islands[1..]
+        .iter()
+        .all(|i| Arc::ptr_eq(pool, i.evaluator.pool.as_ref().unwrap())));
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct RecordImprovements(Arc<std::sync::Mutex<Vec<(f64, bool)>>>, bool);
+
+#[cfg(test)]
+impl Observer<crate::problems::NQueens> for RecordImprovements {
+    fn on_start(&mut self, _: &Config, population: &[Individual<crate::problems::NQueens>]) {
+        self.0.lock().unwrap().push((population[0].fitness, true));
+    }
+
+    fn on_generation(
+        &mut self,
+        stats: &EvolutionStats,
+        _: &[Individual<crate::problems::NQueens>],
+    ) -> Control {
+        self.0.lock().unwrap().push((stats.fitness, self.1));
+        self.1 = false;
+        Control::Continue
+    }
+
+    fn on_improvement(&mut self, _: &EvolutionStats, _: &Individual<crate::problems::NQueens>) {
+        self.1 = true;
+    }
+}
+
+#[test]
+fn test_improvement_is_over_the_best_so_far() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let observer = RecordImprovements::default();
+    let generations = observer.0.clone();
+    let result = GeneticAlgorithm::<NQueens>::builder()
+        .seed(6)
+        .elitism(false)
+        .mutation_rate(1.0)
+        .target_fitness(2.0)
+        .max_generations(100)
+        .observer(observer)
+        .build(12)
+        .evolve();
+
+    // Without elitism the best individual gets lost and found again, which is no improvement
+    let generations = generations.lock().unwrap();
+    let mut best = f64::NEG_INFINITY;
+    let mut recovered = false;
+    for (i, &(fitness, improved)) in generations.iter().enumerate() {
+        recovered |= i > 0 && fitness > generations[i - 1].0 && fitness <= best;
+        assert_eq!(improved, fitness > best);
+        best = best.max(fitness);
+    }
+    assert!(recovered);
+    assert_eq!(result.stats.best_fitness, Some(best));
+}
+
+#[test]
+fn test_stagnation_stops_evolution() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    let result = GeneticAlgorithm::<NQueens>::builder()
+        .seed(5)
+        .target_fitness(2.0)
+        .stagnation(10)
+        .build(12)
+        .evolve();
+
+    assert_eq!(result.termination, TerminationReason::Stagnation);
+    assert_eq!(result.generations - result.stats.last_improvement, 10);
+}
+
+#[test]
+fn test_cellular_population_keeps_grid_order() {
+    use super::builder::GeneticAlgorithm;
+    use crate::problems::NQueens;
+
+    for update in &[CellularUpdate::Synchronous, CellularUpdate::Asynchronous] {
+        let mut ga = GeneticAlgorithm::<NQueens>::builder()
+            .seed(2)
+            .population(36)
+            .population_model(PopulationModel::Cellular)
+            .cellular_update(*update)
+            .target_fitness(2.0)
+            .max_generations(20)
+            .build(12);
+        let result = ga.evolve();
+        let best = ga
+            .individuals()
+            .iter()
+            .map(|i| i.fitness)
+            .fold(0.0, f64::max);
+
+        assert_eq!(result.generations, 20);
+        assert_eq!(ga.individuals().len(), 36);
+        assert_eq!(result.best.fitness, best);
+        assert_eq!(ga.stats().fitness, best);
+
+        // The cells are never reordered, so the grid is not sorted by fitness
+        let individuals = ga.individuals();
+        assert!(!individuals.windows(2).all(|w| w[0].fitness >= w[1].fitness));
+    }
 }
@@ -0,0 +1,170 @@
+/// Results and human readable reporting of an evolution
+use super::builder::Config;
+use super::individual::{Genotype, Individual};
+use super::observer::{Control, Observer};
+use super::population::EvolutionStats;
+use std::fmt;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Why an evolution stopped
+#[derive(Clone, Debug, PartialEq)]
+pub enum TerminationReason {
+    /// The best individual reached the target fitness
+    TargetFitness,
+    /// The maximum number of generations was reached
+    MaxGenerations,
+    /// The time limit was reached
+    TimeLimit,
+    /// The maximum number of fitness evaluations was reached
+    MaxEvaluations,
+    /// The best fitness stopped improving
+    Stagnation,
+    /// The diversity of the population dropped below the threshold
+    Diversity,
+    /// Several conditions that all had to be met
+    AllOf(Vec<TerminationReason>),
+    /// An observer stopped the evolution
+    Stopped,
+    /// The evolution was interrupted from outside
+    Interrupted,
+}
+
+impl Display for TerminationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TerminationReason::TargetFitness => write!(f, "reached target fitness"),
+            TerminationReason::MaxGenerations => write!(f, "reached maximum generations"),
+            TerminationReason::TimeLimit => write!(f, "reached time limit"),
+            TerminationReason::MaxEvaluations => write!(f, "reached maximum evaluations"),
+            TerminationReason::Stagnation => write!(f, "best fitness stopped improving"),
+            TerminationReason::Diversity => write!(f, "population diversity dropped too low"),
+            TerminationReason::AllOf(reasons) => {
+                let reasons: Vec<String> = reasons.iter().map(|r| r.to_string()).collect();
+                write!(f, "{}", reasons.join(" and "))
+            }
+            TerminationReason::Stopped => write!(f, "stopped by observer"),
+            TerminationReason::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+/// Outcome of an evolution
+#[derive(Clone, Debug)]
+pub struct RunResult<T>
+where
+    T: Genotype + PartialOrd,
+{
+    /// The best individual found
+    pub best: Individual<T>,
+    /// Statistics of the final generation
+    pub stats: EvolutionStats,
+    /// Number of generations evolved
+    pub generations: i32,
+    /// Wall clock time spent evolving
+    pub elapsed: Duration,
+    /// Total number of fitness evaluations
+    pub evaluations: u64,
+    /// Why the evolution stopped
+    pub termination: TerminationReason,
+    /// Non-dominated individuals of the final population of a multi-objective evolution,
+    /// empty otherwise
+    pub pareto_front: Vec<Individual<T>>,
+    /// Hypervolume of the Pareto front, for multi-objective evolutions
+    pub hypervolume: Option<f64>,
+}
+
+/// Prints the progress of an evolution to stdout
+#[derive(Debug, Default)]
+pub struct ConsoleReporter {
+    debug: bool,
+    last_print: f32,
+}
+
+impl ConsoleReporter {
+    /// Create a new reporter, printing the entire population as well in debug mode
+    pub fn new(debug: bool) -> Self {
+        ConsoleReporter {
+            debug,
+            last_print: 0.0,
+        }
+    }
+}
+
+/// Print the decoded phenotype of an individual, or its genotype if it was not decoded
+fn print_best<T>(individual: &Individual<T>)
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: Display,
+{
+    match &individual.phenotype {
+        Some(phenotype) => println!("{}", phenotype),
+        None => println!("{}", individual.genotype),
+    }
+}
+
+/// Prints the start, progress and outcome of the evolution
+impl<T> Observer<T> for ConsoleReporter
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: Display,
+{
+    fn on_start(&mut self, config: &Config, _population: &[Individual<T>]) {
+        self.last_print = 0.0;
+
+        if self.debug {
+            println!("{:?}", config);
+        }
+
+        if config.max_generations == 0 {
+            println!(
+                "Attempting to evolve until target fitness {:.3} is met",
+                config.target_fitness
+            );
+        } else {
+            println!(
+                "Attempting to evolve to target fitness {:.3} in maximum {} generations",
+                config.target_fitness, config.max_generations
+            );
+        }
+    }
+
+    /// Output status at most every second
+    fn on_generation(&mut self, stats: &EvolutionStats, population: &[Individual<T>]) -> Control {
+        if stats.elapsed - self.last_print > 1.0 {
+            let best = &population[0];
+
+            println!("{} Best: {}", stats, best);
+            if self.debug {
+                println!(
+                    "{:?}",
+                    population.iter().map(|i| i.fitness).collect::<Vec<f64>>()
+                );
+                print_best(best);
+            }
+            self.last_print = stats.elapsed;
+        }
+
+        Control::Continue
+    }
+
+    fn on_finish(&mut self, result: &RunResult<T>) {
+        println!(
+            "Reached {:.3} fitness in {} generations after {:.3}s with {} mutations and {} crossovers",
+            result.best.fitness,
+            result.generations,
+            result.elapsed.as_secs_f32(),
+            result.stats.total_mutations,
+            result.stats.total_crossovers
+        );
+        println!("Stopped: {}", result.termination);
+        if let Some(hypervolume) = result.hypervolume {
+            println!(
+                "Pareto front of {} individuals with hypervolume {:.3}",
+                result.pareto_front.len(),
+                hypervolume
+            );
+        }
+        print_best(&result.best);
+    }
+}
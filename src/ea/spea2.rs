@@ -0,0 +1,307 @@
+/// SPEA2, the strength Pareto evolutionary algorithm keeping an archive of the best trade-offs
+use super::builder::Config;
+use super::individual::{Genotype, Individual, MultiObjective};
+use super::observer::{Control, Observer};
+use super::pareto::{dominates, Engine};
+use super::population::{create_rng, EvolutionStats, Population, StandardPopulation};
+use super::report::{RunResult, TerminationReason};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::time::SystemTime;
+
+/// Objectives scaled to [0, 1] by their range over all points, so distances do not depend on
+/// the units of each objective
+fn normalize(objectives: &[&[f64]]) -> Vec<Vec<f64>> {
+    let count = objectives.first().map_or(0, |o| o.len());
+    let ranges: Vec<(f64, f64)> = (0..count)
+        .map(|m| {
+            objectives
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), o| {
+                    (low.min(o[m]), high.max(o[m]))
+                })
+        })
+        .collect();
+
+    objectives
+        .iter()
+        .map(|o| {
+            o.iter()
+                .zip(&ranges)
+                .map(|(x, (low, high))| {
+                    if high > low {
+                        (x - low) / (high - low)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn euclidean(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Distances from a point to the other points with the given indices, nearest first
+fn sorted_distances(points: &[Vec<f64>], indices: &[usize], i: usize) -> Vec<(f64, usize)> {
+    let mut distances: Vec<(f64, usize)> = indices
+        .iter()
+        .filter(|&&j| j != i)
+        .map(|&j| (euclidean(&points[i], &points[j]), j))
+        .collect();
+    distances.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    distances
+}
+
+/// SPEA2 fitness of every point, lower is better. The raw fitness is the sum of the strengths
+/// of the points dominating it, where the strength of a point is how many points it dominates.
+/// The density added to it is below 1 and shrinks with the distance to the k-th nearest point,
+/// for k the square root of the number of points, so non-dominated points stay below 1.
+fn strength_fitness(objectives: &[&[f64]], normalized: &[Vec<f64>], minimize: bool) -> Vec<f64> {
+    let n = objectives.len();
+    let all: Vec<usize> = (0..n).collect();
+    let strength: Vec<usize> = (0..n)
+        .map(|i| {
+            (0..n)
+                .filter(|&j| dominates(objectives[i], objectives[j], minimize))
+                .count()
+        })
+        .collect();
+    let k = ((n as f64).sqrt() as usize).max(1);
+
+    (0..n)
+        .map(|i| {
+            let raw: usize = (0..n)
+                .filter(|&j| dominates(objectives[j], objectives[i], minimize))
+                .map(|j| strength[j])
+                .sum();
+            let distances = sorted_distances(normalized, &all, i);
+            let sigma = distances
+                .get(k - 1)
+                .or_else(|| distances.last())
+                .map_or(0.0, |n| n.0);
+
+            raw as f64 + 1.0 / (sigma + 2.0)
+        })
+        .collect()
+}
+
+/// Indices of the points making up the next archive of the given size, the best first. All
+/// non-dominated points are kept, topped up with the best dominated ones, or truncated by
+/// repeatedly removing the point closest to its nearest neighbours.
+fn environmental_selection(normalized: &[Vec<f64>], fitness: &[f64], size: usize) -> Vec<usize> {
+    let by_fitness = |a: &usize, b: &usize| {
+        fitness[*a]
+            .partial_cmp(&fitness[*b])
+            .unwrap_or(Ordering::Equal)
+    };
+    let mut selected: Vec<usize> = (0..fitness.len()).filter(|&i| fitness[i] < 1.0).collect();
+
+    if selected.len() < size {
+        let mut dominated: Vec<usize> = (0..fitness.len()).filter(|&i| fitness[i] >= 1.0).collect();
+        dominated.sort_by(by_fitness);
+        dominated.truncate(size - selected.len());
+        selected.extend(dominated);
+    }
+
+    let mut neighbours: Vec<Vec<(f64, usize)>> = selected
+        .iter()
+        .map(|&i| sorted_distances(normalized, &selected, i))
+        .collect();
+
+    while selected.len() > size {
+        // The point with the nearest neighbour, ties broken by the next nearest and so on
+        let crowded = (0..selected.len())
+            .min_by(|&a, &b| {
+                let a = neighbours[a].iter().map(|n| n.0);
+                let b = neighbours[b].iter().map(|n| n.0);
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+
+        let removed = selected.remove(crowded);
+        neighbours.remove(crowded);
+        for distances in neighbours.iter_mut() {
+            distances.retain(|&(_, j)| j != removed);
+        }
+    }
+
+    selected.sort_by(by_fitness);
+    selected
+}
+
+/// SPEA2 by Zitzler et al. Every generation the offspring and the archive compete for the next
+/// archive by strength fitness and density, and parents are chosen from the archive by binary
+/// tournaments. Distances are measured between objectives normalized to their range.
+pub struct Spea2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    engine: Engine<T>,
+    /// Offspring of the last generation, or the initial population
+    population: Vec<Individual<T>>,
+    /// Individuals kept by environmental selection, the best first
+    archive: Vec<Individual<T>>,
+    /// SPEA2 fitness of every archive member, lower is better
+    strength: Vec<f64>,
+}
+
+/// Evolve the individuals of a population with SPEA2, taking over its observers
+impl<T> From<StandardPopulation<T>> for Spea2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    fn from(population: StandardPopulation<T>) -> Self {
+        let (engine, population) = Engine::new(population);
+
+        Spea2 {
+            engine,
+            population,
+            archive: Vec::new(),
+            strength: Vec::new(),
+        }
+    }
+}
+
+impl<T> Spea2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    /// Fill the archive with the best of the current population and the previous archive
+    fn select_archive(&mut self) {
+        let config = &self.engine.config;
+        let size = config.archive_size.unwrap_or(config.population).max(1);
+
+        let mut candidates = std::mem::take(&mut self.archive);
+        candidates.append(&mut self.population);
+
+        let objectives: Vec<&[f64]> = candidates.iter().map(|i| i.objectives.as_slice()).collect();
+        let normalized = normalize(&objectives);
+        let fitness = strength_fitness(&objectives, &normalized, config.minimize);
+        let kept = environmental_selection(&normalized, &fitness, size);
+
+        let mut candidates: Vec<Option<Individual<T>>> = candidates.into_iter().map(Some).collect();
+        self.archive = kept.iter().filter_map(|&i| candidates[i].take()).collect();
+        self.strength = kept.iter().map(|&i| fitness[i]).collect();
+    }
+
+    /// Binary tournament between archive members, preferring the lower SPEA2 fitness
+    fn tournament(&mut self) -> usize {
+        let n = self.archive.len();
+        let a = self.engine.rng.gen_range(0..n);
+        let b = self.engine.rng.gen_range(0..n);
+
+        if self.strength[b] < self.strength[a] {
+            b
+        } else {
+            a
+        }
+    }
+
+    /// Evaluate the initial population and fill the first archive from it
+    fn initialize(&mut self) {
+        let mut population = std::mem::take(&mut self.population);
+        self.engine.evaluate(&mut population);
+        self.population = population;
+        self.select_archive();
+    }
+
+    /// Breed a generation of offspring from the archive and update the archive with them,
+    /// returning whether any observer wants to stop
+    fn next(&mut self) -> Control {
+        let pool: Vec<usize> = (0..2 * self.engine.config.population)
+            .map(|_| self.tournament())
+            .collect();
+        let mut offspring = self.engine.breed(&self.archive, &pool);
+        self.engine.evaluate(&mut offspring);
+
+        self.population = offspring;
+        self.select_archive();
+
+        self.engine.notify(&self.archive)
+    }
+
+    /// Notify the given observer of the progress of the evolution
+    pub fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.engine.add_observer(observer);
+    }
+
+    /// Members of the archive, the best by SPEA2 fitness first
+    pub fn archive(&self) -> &[Individual<T>] {
+        &self.archive
+    }
+
+    pub fn stats(&self) -> &EvolutionStats {
+        &self.engine.stats
+    }
+}
+
+impl<T> Population for Spea2<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    type Genotype = T;
+
+    /// Evolve the archive until the scalar fitness meets the termination criteria, returning
+    /// the Pareto front of the final archive along with the individual of best fitness
+    fn evolve(&mut self) -> RunResult<T> {
+        self.engine.started = SystemTime::now();
+        self.initialize();
+        self.engine.start(&self.archive);
+
+        let termination = loop {
+            if let Some(reason) = self.engine.check() {
+                break reason;
+            }
+            if self.next() == Control::Stop {
+                break TerminationReason::Stopped;
+            }
+        };
+
+        self.engine.finish(&self.archive, termination)
+    }
+
+    /// Create a new SPEA2 population
+    fn new(config: Config, context: T::Context) -> Self {
+        let rng = create_rng(config.seed);
+        Spea2::from(StandardPopulation::with_rng(config, context, rng))
+    }
+}
+
+#[test]
+fn test_environmental_selection() {
+    let objectives: Vec<&[f64]> = vec![
+        &[0.0, 4.0],
+        &[1.0, 3.0],
+        &[1.1, 2.9],
+        &[4.0, 0.0],
+        &[3.0, 3.0],
+        &[4.0, 4.0],
+    ];
+    let normalized = normalize(&objectives);
+    let fitness = strength_fitness(&objectives, &normalized, true);
+
+    // Four non-dominated points, (3, 3) is dominated by two points of strength 2 and (4, 4) by
+    // all others, with strengths 1, 2, 2, 1 and 1
+    assert!(fitness[..4].iter().all(|&f| f < 1.0));
+    assert_eq!(fitness[4].floor(), 4.0);
+    assert_eq!(fitness[5].floor(), 7.0);
+
+    // Topped up with the best dominated point, or truncated by dropping one of the close pair
+    assert_eq!(environmental_selection(&normalized, &fitness, 5).len(), 5);
+    assert!(environmental_selection(&normalized, &fitness, 5).contains(&4));
+    let truncated = environmental_selection(&normalized, &fitness, 3);
+    assert!(truncated.contains(&0) && truncated.contains(&3));
+    assert!(truncated.contains(&1) != truncated.contains(&2));
+}
@@ -0,0 +1,132 @@
+/// Composable conditions for stopping an evolution
+use super::population::EvolutionStats;
+use super::report::TerminationReason;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A condition under which an evolution stops
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Termination {
+    /// The best individual reaches the fitness, taking minimization into account
+    TargetFitness(f64),
+    /// The given number of generations have been evolved
+    MaxGenerations(u32),
+    /// The evolution has been running for the given wall clock time
+    TimeLimit(Duration),
+    /// The given number of fitness evaluations have been performed
+    MaxEvaluations(u64),
+    /// The best fitness has not improved for the given number of generations
+    Stagnation(u32),
+    /// The fraction of distinct genotypes in the population drops below the threshold
+    Diversity(f64),
+    /// Any of the conditions is met
+    AnyOf(Vec<Termination>),
+    /// All of the conditions are met at the same time
+    AllOf(Vec<Termination>),
+}
+
+impl Termination {
+    /// Whether the condition depends on the diversity of the population
+    pub fn uses_diversity(&self) -> bool {
+        match self {
+            Termination::Diversity(_) => true,
+            Termination::AnyOf(conditions) | Termination::AllOf(conditions) => {
+                conditions.iter().any(Termination::uses_diversity)
+            }
+            _ => false,
+        }
+    }
+
+    /// Check the condition against the statistics of the current generation, returning why
+    /// the evolution should stop if it should
+    pub fn check(&self, stats: &EvolutionStats, minimize: bool) -> Option<TerminationReason> {
+        let (met, reason) = match self {
+            Termination::TargetFitness(target) => {
+                let met = if minimize {
+                    stats.fitness <= *target
+                } else {
+                    stats.fitness >= *target
+                };
+                (met, TerminationReason::TargetFitness)
+            }
+            Termination::MaxGenerations(generations) => (
+                stats.generation >= *generations as i32,
+                TerminationReason::MaxGenerations,
+            ),
+            Termination::TimeLimit(limit) => (
+                stats.elapsed >= limit.as_secs_f32(),
+                TerminationReason::TimeLimit,
+            ),
+            Termination::MaxEvaluations(evaluations) => (
+                stats.evaluations >= *evaluations,
+                TerminationReason::MaxEvaluations,
+            ),
+            Termination::Stagnation(generations) => (
+                stats.generation - stats.last_improvement >= *generations as i32,
+                TerminationReason::Stagnation,
+            ),
+            Termination::Diversity(threshold) => {
+                (stats.diversity < *threshold, TerminationReason::Diversity)
+            }
+            Termination::AnyOf(conditions) => {
+                return conditions.iter().find_map(|c| c.check(stats, minimize));
+            }
+            Termination::AllOf(conditions) => {
+                let reasons: Option<Vec<TerminationReason>> = conditions
+                    .iter()
+                    .map(|c| c.check(stats, minimize))
+                    .collect();
+
+                return reasons
+                    .filter(|r| !r.is_empty())
+                    .map(TerminationReason::AllOf);
+            }
+        };
+
+        if met {
+            Some(reason)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_combined_termination() {
+    let stats = EvolutionStats {
+        generation: 20,
+        last_improvement: 5,
+        fitness: 0.5,
+        diversity: 0.1,
+        ..Default::default()
+    };
+    let stagnated = Termination::AllOf(vec![
+        Termination::Stagnation(10),
+        Termination::Diversity(0.2),
+    ]);
+
+    assert_eq!(Termination::MaxEvaluations(1).check(&stats, false), None);
+    assert_eq!(
+        Termination::AnyOf(vec![
+            Termination::TargetFitness(1.0),
+            Termination::TargetFitness(0.5)
+        ])
+        .check(&stats, false),
+        Some(TerminationReason::TargetFitness)
+    );
+    assert_eq!(
+        stagnated.check(&stats, false),
+        Some(TerminationReason::AllOf(vec![
+            TerminationReason::Stagnation,
+            TerminationReason::Diversity
+        ]))
+    );
+    assert_eq!(
+        Termination::AllOf(vec![
+            Termination::Stagnation(10),
+            Termination::Diversity(0.05)
+        ])
+        .check(&stats, false),
+        None
+    );
+}
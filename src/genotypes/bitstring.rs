@@ -0,0 +1,279 @@
+/// Fixed length binary genotype packed into 64 bit words
+use crate::ea::{Genotype, Phenotype};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::FromIterator;
+use structopt::clap::arg_enum;
+
+const WORD: usize = 64;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available crossover operators for bit strings
+    #[derive(Copy, Clone, Debug)]
+    pub enum BitCrossover {
+        OnePoint,
+        TwoPoint,
+        Uniform,
+    }
+}
+
+/// A string of bits
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BitString {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitString {
+    /// A bit string of the given length with all bits cleared
+    pub fn zeros(len: usize) -> Self {
+        BitString {
+            words: vec![0; len.div_ceil(WORD)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn check(&self, i: usize) {
+        assert!(i < self.len, "bit {} out of range for {} bits", i, self.len);
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.check(i);
+        self.words[i / WORD] >> (i % WORD) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        self.check(i);
+        if value {
+            self.words[i / WORD] |= 1 << (i % WORD);
+        } else {
+            self.words[i / WORD] &= !(1 << (i % WORD));
+        }
+    }
+
+    pub fn flip(&mut self, i: usize) {
+        self.check(i);
+        self.words[i / WORD] ^= 1 << (i % WORD);
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |i| self.get(i))
+    }
+
+    /// Clear the unused bits of the last word, so equal strings have equal words
+    fn mask_last_word(&mut self) {
+        if !self.len.is_multiple_of(WORD) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << (self.len % WORD)) - 1;
+            }
+        }
+    }
+
+    /// Copy of self with the bits in start..end taken from other
+    fn splice(&self, other: &Self, start: usize, end: usize) -> Self {
+        let mut child = self.clone();
+
+        for (w, word) in child.words.iter_mut().enumerate() {
+            let first = w * WORD;
+            let from = start.clamp(first, first + WORD) - first;
+            let to = end.clamp(first, first + WORD) - first;
+
+            if from < to {
+                let mask = if to - from == WORD {
+                    !0
+                } else {
+                    ((1 << (to - from)) - 1) << from
+                };
+                *word = (*word & !mask) | (other.words[w] & mask);
+            }
+        }
+
+        child
+    }
+}
+
+impl FromIterator<bool> for BitString {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bits = BitString::zeros(0);
+
+        for bit in iter {
+            if bits.len.is_multiple_of(WORD) {
+                bits.words.push(0);
+            }
+            bits.len += 1;
+            bits.set(bits.len - 1, bit);
+        }
+
+        bits
+    }
+}
+
+impl fmt::Display for BitString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bits: String = self.iter().map(|b| if b { '1' } else { '0' }).collect();
+        write!(f, "{}", bits)
+    }
+}
+
+/// Everything needed to evolve bit strings for a binary problem
+pub struct BitStringContext {
+    /// Number of bits
+    pub length: usize,
+    /// Crossover operator
+    pub crossover: BitCrossover,
+    /// Probability of flipping each bit when an individual is mutated
+    pub gene_rate: f64,
+    /// Fitness of a bit string
+    pub fitness: Box<dyn Fn(&BitString) -> f64 + Send + Sync>,
+}
+
+impl BitStringContext {
+    /// Bit strings of the given length evaluated by the fitness function, using one-point
+    /// crossover and flipping one bit per mutation on average
+    pub fn new<F>(length: usize, fitness: F) -> Self
+    where
+        F: Fn(&BitString) -> f64 + Send + Sync + 'static,
+    {
+        BitStringContext {
+            length,
+            crossover: BitCrossover::OnePoint,
+            gene_rate: 1.0 / length.max(1) as f64,
+            fitness: Box::new(fitness),
+        }
+    }
+
+    pub fn crossover(mut self, crossover: BitCrossover) -> Self {
+        self.crossover = crossover;
+        self
+    }
+
+    pub fn gene_rate(mut self, gene_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&gene_rate),
+            "gene rate must be between 0 and 1"
+        );
+        self.gene_rate = gene_rate;
+        self
+    }
+}
+
+impl Genotype for BitString {
+    type Context = BitStringContext;
+    type Phenotype = Self;
+
+    /// Create a random bit string
+    fn new(rng: &mut impl Rng, context: &BitStringContext) -> Self {
+        let mut bits = BitString::zeros(context.length);
+
+        for word in bits.words.iter_mut() {
+            *word = rng.gen();
+        }
+        bits.mask_last_word();
+
+        bits
+    }
+
+    fn decode(&self, _context: &BitStringContext) -> Self {
+        self.clone()
+    }
+
+    /// Flip every bit with the per gene rate of the context
+    fn mutate(&mut self, context: &BitStringContext, rng: &mut impl Rng) {
+        for i in 0..self.len {
+            if rng.gen_bool(context.gene_rate) {
+                self.flip(i);
+            }
+        }
+    }
+
+    fn crossover(&self, other: &Self, context: &BitStringContext, rng: &mut impl Rng) -> Self {
+        let len = self.len;
+
+        if len < 2 {
+            return self.clone();
+        }
+
+        match context.crossover {
+            BitCrossover::OnePoint => self.splice(other, rng.gen_range(1..len), len),
+            BitCrossover::TwoPoint => {
+                let a = rng.gen_range(0..len);
+                let b = rng.gen_range(0..len);
+                self.splice(other, a.min(b), a.max(b) + 1)
+            }
+            BitCrossover::Uniform => {
+                let mut child = self.clone();
+
+                for (w, word) in child.words.iter_mut().enumerate() {
+                    let mask: u64 = rng.gen();
+                    *word = (*word & mask) | (other.words[w] & !mask);
+                }
+
+                child
+            }
+        }
+    }
+}
+
+impl Phenotype<BitStringContext> for BitString {
+    fn fitness(&self, context: &BitStringContext) -> f64 {
+        (context.fitness)(self)
+    }
+}
+
+#[test]
+fn test_packed_bits() {
+    let mut bits: BitString = (0..130).map(|i| i % 3 == 0).collect();
+
+    assert_eq!(bits.len(), 130);
+    assert_eq!(bits.count_ones(), 44);
+    assert!(bits.get(129));
+    bits.flip(129);
+    bits.set(64, true);
+    assert!(!bits.get(129));
+    assert!(bits.get(64));
+    assert_eq!(bits.count_ones(), 44);
+}
+
+#[test]
+fn test_crossover_takes_bits_from_parents() {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let ones: BitString = (0..150).map(|_| true).collect();
+    let zeros = BitString::zeros(150);
+
+    for crossover in &[BitCrossover::OnePoint, BitCrossover::TwoPoint] {
+        let context = BitStringContext::new(150, |b| b.count_ones() as f64).crossover(*crossover);
+
+        for _ in 0..20 {
+            let child = ones.crossover(&zeros, &context, &mut rng);
+            let bits: Vec<bool> = child.iter().collect();
+            // Bits from the other parent form a single contiguous segment
+            let switches = bits.windows(2).filter(|w| w[0] != w[1]).count();
+
+            assert!(switches <= 2);
+        }
+    }
+
+    let context =
+        BitStringContext::new(150, |b| b.count_ones() as f64).crossover(BitCrossover::Uniform);
+    let child = ones.crossover(&zeros, &context, &mut rng);
+    assert!(child.count_ones() > 40 && child.count_ones() < 110);
+    assert_eq!(child.words[2] >> 22, 0);
+}
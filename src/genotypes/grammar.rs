@@ -0,0 +1,390 @@
+/// Grammatical evolution, mapping integer codons through a BNF grammar to a program
+use crate::ea::{Genotype, Phenotype};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fmt, fs, io};
+
+/// Give up mapping after this many expansions, in case a grammar loops without choices
+const MAX_EXPANSIONS: usize = 100_000;
+
+/// Errors that can occur while loading a grammar
+#[derive(Debug)]
+pub enum GrammarError {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrammarError::Io(e) => write!(f, "{}", e),
+            GrammarError::Parse(message) => write!(f, "Invalid grammar: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for GrammarError {
+    fn from(e: io::Error) -> Self {
+        GrammarError::Io(e)
+    }
+}
+
+fn parse_error<T>(message: String) -> Result<T, GrammarError> {
+    Err(GrammarError::Parse(message))
+}
+
+/// A symbol in the production of a rule
+#[derive(Clone, Debug, PartialEq)]
+pub enum Symbol {
+    Terminal(String),
+    /// Index of the rule of a non-terminal
+    NonTerminal(usize),
+}
+
+/// A context free grammar in Backus-Naur form
+#[derive(Clone, Debug)]
+pub struct Grammar {
+    /// Names of the non-terminals, the first one is the start symbol
+    names: Vec<String>,
+    /// Alternative productions of every non-terminal
+    rules: Vec<Vec<Vec<Symbol>>>,
+}
+
+/// Split the right hand side of a rule into its alternatives, each a list of tokens where
+/// non-terminals keep their angle brackets
+fn tokenize(text: &str) -> Result<Vec<Vec<String>>, GrammarError> {
+    let mut alternatives = vec![Vec::new()];
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let tokens = alternatives.last_mut().unwrap();
+
+        match c {
+            '|' => alternatives.push(Vec::new()),
+            c if c.is_whitespace() => {}
+            '"' | '\'' => {
+                let literal: String = chars.by_ref().take_while(|&q| q != c).collect();
+                // Quotes mark terminals, so an empty literal still needs to be a terminal
+                tokens.push(format!("{}{}", c, literal));
+            }
+            '<' => {
+                let name: String = chars.by_ref().take_while(|&q| q != '>').collect();
+                tokens.push(format!("<{}>", name));
+            }
+            c => {
+                let mut bare = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "<|\"'".contains(next) {
+                        break;
+                    }
+                    bare.push(next);
+                    chars.next();
+                }
+                tokens.push(format!("\"{}", bare));
+            }
+        }
+    }
+
+    if alternatives.iter().any(|a| a.is_empty()) {
+        return parse_error(format!("empty alternative in {:?}", text.trim()));
+    }
+
+    Ok(alternatives)
+}
+
+impl Grammar {
+    /// Parse a grammar with one rule per line, like `<expr> ::= <expr> + <expr> | x`.
+    /// Lines starting with `|` continue the previous rule and lines starting with `#` are
+    /// comments. Terminals are concatenated without separators, so spaces have to be quoted.
+    pub fn parse(text: &str) -> Result<Grammar, GrammarError> {
+        let mut definitions: Vec<(String, String)> = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('|') {
+                match definitions.last_mut() {
+                    Some((_, body)) => {
+                        body.push_str(" | ");
+                        body.push_str(rest);
+                    }
+                    None => return parse_error(format!("no rule to continue: {}", line)),
+                }
+                continue;
+            }
+
+            match line.split_once("::=") {
+                Some((name, body)) => {
+                    let name = name.trim();
+                    if !(name.starts_with('<') && name.ends_with('>')) {
+                        return parse_error(format!("invalid non-terminal {}", name));
+                    }
+                    definitions.push((name.to_string(), body.to_string()));
+                }
+                None => return parse_error(format!("expected ::= in {}", line)),
+            }
+        }
+
+        if definitions.is_empty() {
+            return parse_error("no rules".to_string());
+        }
+
+        let names: Vec<String> = definitions.iter().map(|(name, _)| name.clone()).collect();
+        let index: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+        if index.len() != names.len() {
+            return parse_error("non-terminal defined twice".to_string());
+        }
+
+        let mut rules = Vec::with_capacity(definitions.len());
+        for (_, body) in &definitions {
+            let mut productions = Vec::new();
+
+            for tokens in tokenize(body)? {
+                let mut production = Vec::with_capacity(tokens.len());
+
+                for token in tokens {
+                    if token.starts_with('<') {
+                        match index.get(token.as_str()) {
+                            Some(&rule) => production.push(Symbol::NonTerminal(rule)),
+                            None => {
+                                return parse_error(format!("undefined non-terminal {}", token))
+                            }
+                        }
+                    } else {
+                        production.push(Symbol::Terminal(token[1..].to_string()));
+                    }
+                }
+                productions.push(production);
+            }
+            rules.push(productions);
+        }
+
+        Ok(Grammar { names, rules })
+    }
+
+    /// Load a grammar from a BNF file
+    pub fn load(path: &Path) -> Result<Grammar, GrammarError> {
+        Grammar::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Name of the start symbol
+    pub fn start(&self) -> &str {
+        &self.names[0]
+    }
+
+    /// Map the codons to a program by always expanding the leftmost non-terminal with the
+    /// production chosen by the next codon, modulo the number of productions. Rules with a
+    /// single production do not use a codon. Once the codons run out they are reused from
+    /// the start up to `max_wraps` times, after which the individual is invalid and None is
+    /// returned.
+    pub fn map(&self, codons: &[u8], max_wraps: usize) -> Option<String> {
+        let mut program = String::new();
+        let mut stack = vec![Symbol::NonTerminal(0)];
+        let mut used = 0;
+        let budget = codons.len() * (max_wraps + 1);
+
+        for _ in 0..MAX_EXPANSIONS {
+            let rule = match stack.pop() {
+                Some(Symbol::Terminal(terminal)) => {
+                    program.push_str(&terminal);
+                    continue;
+                }
+                Some(Symbol::NonTerminal(rule)) => rule,
+                None => return Some(program),
+            };
+
+            let productions = &self.rules[rule];
+            let choice = if productions.len() == 1 {
+                0
+            } else if used < budget {
+                used += 1;
+                codons[(used - 1) % codons.len()] as usize % productions.len()
+            } else {
+                return None;
+            };
+
+            stack.extend(productions[choice].iter().rev().cloned());
+        }
+
+        None
+    }
+}
+
+/// A string of 8 bit codons, as in the original grammatical evolution
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Codons {
+    pub codons: Vec<u8>,
+}
+
+impl fmt::Display for Codons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.codons)
+    }
+}
+
+/// Evaluator of the programs produced by a grammar
+pub type ProgramEvaluator = dyn Fn(&str) -> f64 + Send + Sync;
+
+/// Grammar, mapping settings and evaluator of a grammatical evolution problem
+pub struct GrammarContext {
+    pub grammar: Grammar,
+    /// Number of codons of new individuals
+    pub length: usize,
+    /// Crossover does not produce more codons than this
+    pub max_length: usize,
+    /// Number of times the codons may be reused when mapping
+    pub max_wraps: usize,
+    /// Probability of replacing each codon when an individual is mutated
+    pub gene_rate: f64,
+    /// Fitness of individuals that do not map to a program, the worst possible by default
+    pub invalid_fitness: f64,
+    /// Fitness of a program
+    pub evaluator: Box<ProgramEvaluator>,
+}
+
+impl GrammarContext {
+    /// Individuals of 100 codons mapped through the grammar with up to 2 wraps and evaluated
+    /// by the evaluator, which should be minimized
+    pub fn new<F>(grammar: Grammar, evaluator: F) -> Self
+    where
+        F: Fn(&str) -> f64 + Send + Sync + 'static,
+    {
+        GrammarContext {
+            grammar,
+            length: 100,
+            max_length: 400,
+            max_wraps: 2,
+            gene_rate: 0.01,
+            invalid_fitness: f64::INFINITY,
+            evaluator: Box::new(evaluator),
+        }
+    }
+
+    pub fn length(mut self, length: usize) -> Self {
+        assert!(length > 0, "individuals need at least one codon");
+        self.length = length;
+        self.max_length = self.max_length.max(length);
+        self
+    }
+
+    pub fn max_wraps(mut self, max_wraps: usize) -> Self {
+        self.max_wraps = max_wraps;
+        self
+    }
+
+    pub fn gene_rate(mut self, gene_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&gene_rate),
+            "gene rate must be between 0 and 1"
+        );
+        self.gene_rate = gene_rate;
+        self
+    }
+
+    pub fn invalid_fitness(mut self, invalid_fitness: f64) -> Self {
+        self.invalid_fitness = invalid_fitness;
+        self
+    }
+}
+
+/// The program produced by mapping codons through a grammar, None for invalid individuals
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program(pub Option<String>);
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Some(program) => write!(f, "{}", program),
+            None => write!(f, "<invalid>"),
+        }
+    }
+}
+
+impl Genotype for Codons {
+    type Context = GrammarContext;
+    type Phenotype = Program;
+
+    fn new(rng: &mut impl Rng, context: &GrammarContext) -> Self {
+        Codons {
+            codons: (0..context.length).map(|_| rng.gen()).collect(),
+        }
+    }
+
+    /// Map the codons through the grammar of the context
+    fn decode(&self, context: &GrammarContext) -> Program {
+        Program(context.grammar.map(&self.codons, context.max_wraps))
+    }
+
+    /// Replace every codon by a random one with the per gene rate of the context
+    fn mutate(&mut self, context: &GrammarContext, rng: &mut impl Rng) {
+        for codon in self.codons.iter_mut() {
+            if rng.gen_bool(context.gene_rate) {
+                *codon = rng.gen();
+            }
+        }
+    }
+
+    /// One-point crossover with a separate cut point in each parent, so lengths can vary
+    fn crossover(&self, other: &Self, context: &GrammarContext, rng: &mut impl Rng) -> Self {
+        let a = rng.gen_range(0..=self.codons.len());
+        let b = rng.gen_range(0..=other.codons.len());
+        let mut codons: Vec<u8> = self.codons[..a]
+            .iter()
+            .chain(&other.codons[b..])
+            .copied()
+            .take(context.max_length)
+            .collect();
+
+        if codons.is_empty() {
+            codons.push(rng.gen());
+        }
+
+        Codons { codons }
+    }
+
+    fn size(&self) -> usize {
+        self.codons.len()
+    }
+}
+
+impl Phenotype<GrammarContext> for Program {
+    fn fitness(&self, context: &GrammarContext) -> f64 {
+        match &self.0 {
+            Some(program) => (context.evaluator)(program),
+            None => context.invalid_fitness,
+        }
+    }
+}
+
+#[test]
+fn test_grammar_mapping() {
+    let grammar = Grammar::parse(
+        "# Arithmetic over x
+        <expr> ::= <expr><op><expr> | (<expr>) | <var>
+        <op>   ::= + | '-' | \"*\"
+        <var>  ::= x
+               | 1.0",
+    )
+    .unwrap();
+
+    assert_eq!(grammar.start(), "<expr>");
+    assert_eq!(grammar.rules[1].len(), 3);
+    // expr -> expr op expr, expr -> var -> x, op -> *, expr -> (expr) -> var -> 1.0
+    assert_eq!(grammar.map(&[0, 2, 0, 2, 1, 2, 1], 0).unwrap(), "x*(1.0)");
+    // The codons run out without wrapping, or keep choosing expr op expr when wrapped
+    assert_eq!(grammar.map(&[0, 2, 0], 0), None);
+    assert_eq!(grammar.map(&[0], 10), None);
+    assert_eq!(grammar.map(&[2], 0), None);
+    assert_eq!(grammar.map(&[2], 1).unwrap(), "x");
+
+    assert!(Grammar::parse("<a> ::= <b>").is_err());
+    assert!(Grammar::parse("<a> ::= x | | y").is_err());
+}
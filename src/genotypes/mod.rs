@@ -0,0 +1,18 @@
+mod bitstring;
+mod grammar;
+mod permutation;
+mod realvector;
+mod tree;
+
+pub use bitstring::{BitCrossover, BitString, BitStringContext};
+pub use grammar::{
+    Codons, Grammar, GrammarContext, GrammarError, Program, ProgramEvaluator, Symbol,
+};
+pub use permutation::{
+    crossover_permutations, mutate_permutation, random_permutation, shift_elements, Permutation,
+    PermutationContext, PermutationCrossover, PermutationFitness, PermutationMutation,
+};
+pub use realvector::{
+    BoundHandling, RealCrossover, RealFitness, RealMutation, RealVector, RealVectorContext,
+};
+pub use tree::{ExpressionTree, Function, Node, TreeContext, TreeFitness, TreeMutation};
@@ -0,0 +1,456 @@
+/// Permutations of 0..n for ordering problems, with order preserving operators
+use crate::ea::{Genotype, Phenotype};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available order preserving crossover operators for permutations
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub enum PermutationCrossover {
+        PartiallyMapped,
+        Order,
+        Cycle,
+        EdgeRecombination,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available mutation operators for permutations
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub enum PermutationMutation {
+        Swap,
+        Insert,
+        Inversion,
+        Scramble,
+        Displacement,
+    }
+}
+
+/// A random permutation of 0..length
+pub fn random_permutation(length: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let mut permutation: Vec<usize> = Vec::with_capacity(length);
+
+    permutation.extend(0..length);
+    permutation.shuffle(rng);
+
+    permutation
+}
+
+/// Shift the elements "from" index "to" index by an offset of "shift".
+/// "shift" will wrap by the length of the array
+pub fn shift_elements(genome: &mut [usize], from: usize, to: usize, shift: usize) {
+    if from >= to {
+        panic!("from >= to, from={}, to={}", from, to);
+    }
+
+    for i in (from..to).rev() {
+        let pos = (i + shift) % genome.len();
+
+        genome.swap(i, pos);
+    }
+}
+
+/// Pick a random segment [from, to) of a genome with the given length
+fn random_segment(length: usize, rng: &mut impl Rng) -> (usize, usize) {
+    let a = rng.gen_range(0..=length);
+    let b = rng.gen_range(0..=length);
+
+    (a.min(b), a.max(b))
+}
+
+/// Two distinct random positions
+fn random_pair(length: usize, rng: &mut impl Rng) -> (usize, usize) {
+    let a = rng.gen_range(0..length);
+    let mut b = rng.gen_range(0..length - 1);
+
+    if b >= a {
+        b += 1;
+    }
+
+    (a, b)
+}
+
+/// Position of every element in the given permutation
+fn positions(permutation: &[usize]) -> Vec<usize> {
+    let mut positions = vec![0; permutation.len()];
+
+    for (i, &element) in permutation.iter().enumerate() {
+        positions[element] = i;
+    }
+
+    positions
+}
+
+/// Partially mapped crossover (PMX). Copies a segment from a and fills the rest from b,
+/// following the mapping defined by the segment to resolve conflicts.
+fn partially_mapped_crossover(a: &[usize], b: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+    let (from, to) = random_segment(a.len(), rng);
+    let position_a = positions(a);
+    let mut child = b.to_vec();
+
+    child[from..to].copy_from_slice(&a[from..to]);
+
+    for i in (0..from).chain(to..a.len()) {
+        let mut element = b[i];
+
+        // Follow the mapping until we find an element not already in the copied segment
+        while (from..to).contains(&position_a[element]) {
+            element = b[position_a[element]];
+        }
+
+        child[i] = element;
+    }
+
+    child
+}
+
+/// Order crossover (OX). Copies a segment from a and fills the remaining positions with
+/// the elements of b in the order they appear after the segment.
+fn order_crossover(a: &[usize], b: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+    let length = a.len();
+    let (from, to) = random_segment(length, rng);
+    let mut used = vec![false; length];
+    let mut child = a.to_vec();
+
+    for &element in &a[from..to] {
+        used[element] = true;
+    }
+
+    let mut position = to % length;
+    for i in 0..length {
+        let element = b[(to + i) % length];
+
+        if !used[element] {
+            child[position] = element;
+            position = (position + 1) % length;
+        }
+    }
+
+    child
+}
+
+/// Cycle crossover (CX). Every element keeps the position it has in one of the parents,
+/// alternating between the parents for each cycle.
+fn cycle_crossover(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let length = a.len();
+    let position_a = positions(a);
+    let mut child = vec![0; length];
+    let mut visited = vec![false; length];
+    let mut from_a = true;
+
+    for start in 0..length {
+        if visited[start] {
+            continue;
+        }
+
+        let mut i = start;
+        while !visited[i] {
+            visited[i] = true;
+            child[i] = if from_a { a[i] } else { b[i] };
+            i = position_a[b[i]];
+        }
+
+        from_a = !from_a;
+    }
+
+    child
+}
+
+/// Edge recombination crossover (ERX). Builds a cyclic order that uses the adjacencies of
+/// the parents as much as possible, always moving to the neighbour with the fewest remaining
+/// edges.
+fn edge_recombination_crossover(a: &[usize], b: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+    let length = a.len();
+    let mut edges: Vec<Vec<usize>> = vec![Vec::with_capacity(4); length];
+
+    for parent in &[a, b] {
+        for i in 0..length {
+            let element = parent[i];
+
+            for &neighbour in &[parent[(i + length - 1) % length], parent[(i + 1) % length]] {
+                if neighbour != element && !edges[element].contains(&neighbour) {
+                    edges[element].push(neighbour);
+                }
+            }
+        }
+    }
+
+    let mut child = Vec::with_capacity(length);
+    let mut visited = vec![false; length];
+    let mut element = if rng.gen_bool(0.5) { a[0] } else { b[0] };
+
+    loop {
+        child.push(element);
+        visited[element] = true;
+
+        if child.len() == length {
+            break;
+        }
+
+        // Edges are symmetric, so the element only needs removing from its neighbours
+        for neighbour in edges[element].clone() {
+            edges[neighbour].retain(|&c| c != element);
+        }
+
+        let candidates = &edges[element];
+        element = if candidates.is_empty() {
+            let unvisited: Vec<usize> = (0..length).filter(|&c| !visited[c]).collect();
+            *unvisited.choose(rng).unwrap()
+        } else {
+            let fewest = candidates.iter().map(|&c| edges[c].len()).min().unwrap();
+            let best: Vec<usize> = candidates
+                .iter()
+                .copied()
+                .filter(|&c| edges[c].len() == fewest)
+                .collect();
+            *best.choose(rng).unwrap()
+        };
+    }
+
+    child
+}
+
+/// Recombine two permutations of the same elements with the given operator
+pub fn crossover_permutations(
+    crossover: PermutationCrossover,
+    a: &[usize],
+    b: &[usize],
+    rng: &mut impl Rng,
+) -> Vec<usize> {
+    // Order crossover and ERX wrap around the permutation, which an empty one cannot do
+    if a.is_empty() {
+        return Vec::new();
+    }
+
+    match crossover {
+        PermutationCrossover::PartiallyMapped => partially_mapped_crossover(a, b, rng),
+        PermutationCrossover::Order => order_crossover(a, b, rng),
+        PermutationCrossover::Cycle => cycle_crossover(a, b),
+        PermutationCrossover::EdgeRecombination => edge_recombination_crossover(a, b, rng),
+    }
+}
+
+/// Mutate a permutation in place with the given operator
+pub fn mutate_permutation(
+    mutation: PermutationMutation,
+    permutation: &mut Vec<usize>,
+    rng: &mut impl Rng,
+) {
+    let length = permutation.len();
+
+    if length < 2 {
+        return;
+    }
+
+    match mutation {
+        PermutationMutation::Swap => {
+            let (a, b) = random_pair(length, rng);
+            permutation.swap(a, b);
+        }
+        PermutationMutation::Insert => {
+            let (from, to) = random_pair(length, rng);
+            let element = permutation.remove(from);
+            permutation.insert(to, element);
+        }
+        PermutationMutation::Inversion => {
+            let (from, to) = random_segment(length, rng);
+            permutation[from..to].reverse();
+        }
+        PermutationMutation::Scramble => {
+            let (from, to) = random_segment(length, rng);
+            permutation[from..to].shuffle(rng);
+        }
+        PermutationMutation::Displacement => {
+            let (from, to) = random_segment(length, rng);
+            let segment: Vec<usize> = permutation.drain(from..to).collect();
+            let position = rng.gen_range(0..=permutation.len());
+            permutation.splice(position..position, segment);
+        }
+    }
+}
+
+/// An ordering of the elements 0..n
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Permutation {
+    pub order: Vec<usize>,
+}
+
+impl fmt::Display for Permutation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.order)
+    }
+}
+
+/// Fitness function of the order of a permutation
+pub type PermutationFitness = dyn Fn(&[usize]) -> f64 + Send + Sync;
+
+/// Everything needed to evolve permutations for an ordering problem
+pub struct PermutationContext {
+    /// Number of elements
+    pub length: usize,
+    /// Crossover operator
+    pub crossover: PermutationCrossover,
+    /// Mutation operator
+    pub mutation: PermutationMutation,
+    /// Fitness of an order
+    pub fitness: Box<PermutationFitness>,
+}
+
+impl PermutationContext {
+    /// Permutations of 0..length evaluated by the fitness function, using order crossover
+    /// and swap mutation
+    pub fn new<F>(length: usize, fitness: F) -> Self
+    where
+        F: Fn(&[usize]) -> f64 + Send + Sync + 'static,
+    {
+        PermutationContext {
+            length,
+            crossover: PermutationCrossover::Order,
+            mutation: PermutationMutation::Swap,
+            fitness: Box::new(fitness),
+        }
+    }
+
+    pub fn crossover(mut self, crossover: PermutationCrossover) -> Self {
+        self.crossover = crossover;
+        self
+    }
+
+    pub fn mutation(mut self, mutation: PermutationMutation) -> Self {
+        self.mutation = mutation;
+        self
+    }
+}
+
+impl Genotype for Permutation {
+    type Context = PermutationContext;
+    type Phenotype = Self;
+
+    fn new(rng: &mut impl Rng, context: &PermutationContext) -> Self {
+        Permutation {
+            order: random_permutation(context.length, rng),
+        }
+    }
+
+    fn decode(&self, _context: &PermutationContext) -> Self {
+        self.clone()
+    }
+
+    fn mutate(&mut self, context: &PermutationContext, rng: &mut impl Rng) {
+        mutate_permutation(context.mutation, &mut self.order, rng);
+    }
+
+    fn crossover(&self, other: &Self, context: &PermutationContext, rng: &mut impl Rng) -> Self {
+        Permutation {
+            order: crossover_permutations(context.crossover, &self.order, &other.order, rng),
+        }
+    }
+}
+
+impl Phenotype<PermutationContext> for Permutation {
+    fn fitness(&self, context: &PermutationContext) -> f64 {
+        (context.fitness)(&self.order)
+    }
+}
+
+#[test]
+fn test_mutate() {
+    let mut cities = vec![1, 2, 3, 4, 5];
+    shift_elements(&mut cities, 0, 2, 1);
+    assert_eq!(cities, [3, 1, 2, 4, 5]);
+
+    cities = vec![1, 2, 3, 4, 5];
+    shift_elements(&mut cities, 3, 5, 2);
+    assert_eq!(cities, [4, 5, 3, 1, 2]);
+
+    cities = vec![1, 2, 3, 4, 5];
+    shift_elements(&mut cities, 0, 3, 1);
+    assert_eq!(cities, [4, 1, 2, 3, 5]);
+
+    cities = vec![1, 2, 3, 4, 5];
+    shift_elements(&mut cities, 0, 5, 5);
+    assert_eq!(cities, [1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_operators_produce_permutations() {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+    let crossovers = PermutationCrossover::variants();
+    let mutations = PermutationMutation::variants();
+
+    for _ in 0..100 {
+        let length = rng.gen_range(1..30);
+        let a = random_permutation(length, &mut rng);
+        let b = random_permutation(length, &mut rng);
+        let mut offspring: Vec<Vec<usize>> = crossovers
+            .iter()
+            .map(|c| crossover_permutations(c.parse().unwrap(), &a, &b, &mut rng))
+            .collect();
+
+        for m in mutations.iter() {
+            let mut mutated = a.clone();
+            mutate_permutation(m.parse().unwrap(), &mut mutated, &mut rng);
+            offspring.push(mutated);
+        }
+
+        for mut child in offspring {
+            child.sort_unstable();
+            assert_eq!(child, (0..length).collect::<Vec<usize>>());
+        }
+    }
+
+    for c in crossovers.iter() {
+        assert!(crossover_permutations(c.parse().unwrap(), &[], &[], &mut rng).is_empty());
+    }
+}
+
+#[test]
+fn test_cycle_crossover() {
+    let a = [0, 1, 2, 3, 4, 5, 6, 7];
+    let b = [1, 2, 0, 4, 3, 6, 7, 5];
+
+    // Cycles are {0, 1, 2}, {3, 4} and {5, 6, 7}
+    assert_eq!(cycle_crossover(&a, &b), [0, 1, 2, 4, 3, 5, 6, 7]);
+}
+
+#[test]
+fn test_sorting_is_learned() {
+    use crate::ea::{GeneticAlgorithm, Population};
+
+    // Total distance of every element from its sorted position
+    let displacement = |order: &[usize]| {
+        order
+            .iter()
+            .enumerate()
+            .map(|(i, &e)| (i as f64 - e as f64).abs())
+            .sum::<f64>()
+    };
+
+    for mutation in &[
+        PermutationMutation::Swap,
+        PermutationMutation::Insert,
+        PermutationMutation::Inversion,
+        PermutationMutation::Displacement,
+    ] {
+        let context = PermutationContext::new(10, displacement)
+            .crossover(PermutationCrossover::Cycle)
+            .mutation(*mutation);
+        let result = GeneticAlgorithm::<Permutation>::builder()
+            .seed(5)
+            .minimize(true)
+            .target_fitness(0.0)
+            .max_generations(300)
+            .build(context)
+            .evolve();
+
+        assert_eq!(result.best.fitness, 0.0, "{:?}", mutation);
+        assert_eq!(result.best.genotype.order, (0..10).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,317 @@
+/// Fixed length vector of real numbers for continuous optimisation
+use crate::ea::{Genotype, Phenotype};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fmt;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available crossover operators for real vectors
+    #[derive(Copy, Clone, Debug)]
+    pub enum RealCrossover {
+        SimulatedBinary,
+        BlendAlpha,
+        Arithmetic,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available mutation operators for real vectors
+    #[derive(Copy, Clone, Debug)]
+    pub enum RealMutation {
+        Gaussian,
+        Polynomial,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// How values outside their bounds are brought back
+    #[derive(Copy, Clone, Debug)]
+    pub enum BoundHandling {
+        Clamp,
+        Reflect,
+        Resample,
+    }
+}
+
+/// Draw from the standard normal distribution using the Box-Muller transform
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    // 1 - [0, 1) avoids taking the logarithm of zero
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Bring a value back within its bounds
+fn repair(x: f64, (low, high): (f64, f64), handling: BoundHandling, rng: &mut impl Rng) -> f64 {
+    if (low..=high).contains(&x) {
+        return x;
+    }
+
+    match handling {
+        BoundHandling::Clamp => x.clamp(low, high),
+        BoundHandling::Reflect => {
+            let width = high - low;
+
+            if width <= 0.0 {
+                return low;
+            }
+
+            // Mirror back and forth between the bounds until the value lands inside
+            let y = (x - low).rem_euclid(2.0 * width);
+            if y > width {
+                high - (y - width)
+            } else {
+                low + y
+            }
+        }
+        BoundHandling::Resample => rng.gen_range(low..=high),
+    }
+}
+
+/// Simulated binary crossover of two values, returning one of the two children
+fn simulated_binary(a: f64, b: f64, eta: f64, rng: &mut impl Rng) -> f64 {
+    let u: f64 = rng.gen();
+    let beta = if u <= 0.5 {
+        (2.0 * u).powf(1.0 / (eta + 1.0))
+    } else {
+        (1.0 / (2.0 * (1.0 - u))).powf(1.0 / (eta + 1.0))
+    };
+
+    if rng.gen_bool(0.5) {
+        0.5 * ((1.0 + beta) * a + (1.0 - beta) * b)
+    } else {
+        0.5 * ((1.0 - beta) * a + (1.0 + beta) * b)
+    }
+}
+
+/// Polynomial perturbation as a fraction of the range of a value
+fn polynomial_delta(eta: f64, rng: &mut impl Rng) -> f64 {
+    let u: f64 = rng.gen();
+
+    if u < 0.5 {
+        (2.0 * u).powf(1.0 / (eta + 1.0)) - 1.0
+    } else {
+        1.0 - (2.0 * (1.0 - u)).powf(1.0 / (eta + 1.0))
+    }
+}
+
+/// A vector of real numbers
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct RealVector {
+    pub values: Vec<f64>,
+}
+
+impl fmt::Display for RealVector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|v| format!("{:.6}", v)).collect();
+        write!(f, "[{}]", values.join(", "))
+    }
+}
+
+/// Fitness function of the values of a real vector
+pub type RealFitness = dyn Fn(&[f64]) -> f64 + Send + Sync;
+
+/// Everything needed to evolve real vectors for a continuous problem
+pub struct RealVectorContext {
+    /// Lower and upper bound of every dimension
+    pub bounds: Vec<(f64, f64)>,
+    /// Crossover operator
+    pub crossover: RealCrossover,
+    /// Mutation operator
+    pub mutation: RealMutation,
+    /// How values pushed outside their bounds are brought back
+    pub bound_handling: BoundHandling,
+    /// Probability of mutating each value when an individual is mutated
+    pub gene_rate: f64,
+    /// Distribution index of simulated binary crossover, higher keeps children closer to
+    /// their parents
+    pub sbx_eta: f64,
+    /// Distribution index of polynomial mutation
+    pub polynomial_eta: f64,
+    /// How far BLX-α samples beyond the parents, as a fraction of their distance
+    pub alpha: f64,
+    /// Standard deviation of Gaussian mutation, as a fraction of the range of a dimension
+    pub sigma: f64,
+    /// Fitness of a vector
+    pub fitness: Box<RealFitness>,
+}
+
+impl RealVectorContext {
+    /// Vectors within the given bounds evaluated by the fitness function, using simulated
+    /// binary crossover and polynomial mutation of one value per mutation on average
+    pub fn new<F>(bounds: Vec<(f64, f64)>, fitness: F) -> Self
+    where
+        F: Fn(&[f64]) -> f64 + Send + Sync + 'static,
+    {
+        RealVectorContext {
+            gene_rate: 1.0 / bounds.len().max(1) as f64,
+            bounds,
+            crossover: RealCrossover::SimulatedBinary,
+            mutation: RealMutation::Polynomial,
+            bound_handling: BoundHandling::Reflect,
+            sbx_eta: 15.0,
+            polynomial_eta: 20.0,
+            alpha: 0.5,
+            sigma: 0.1,
+            fitness: Box::new(fitness),
+        }
+    }
+
+    pub fn crossover(mut self, crossover: RealCrossover) -> Self {
+        self.crossover = crossover;
+        self
+    }
+
+    pub fn mutation(mut self, mutation: RealMutation) -> Self {
+        self.mutation = mutation;
+        self
+    }
+
+    pub fn bound_handling(mut self, bound_handling: BoundHandling) -> Self {
+        self.bound_handling = bound_handling;
+        self
+    }
+
+    pub fn gene_rate(mut self, gene_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&gene_rate),
+            "gene rate must be between 0 and 1"
+        );
+        self.gene_rate = gene_rate;
+        self
+    }
+}
+
+impl Genotype for RealVector {
+    type Context = RealVectorContext;
+    type Phenotype = Self;
+
+    /// Create a vector uniformly distributed within the bounds
+    fn new(rng: &mut impl Rng, context: &RealVectorContext) -> Self {
+        RealVector {
+            values: context
+                .bounds
+                .iter()
+                .map(|&(low, high)| rng.gen_range(low..=high))
+                .collect(),
+        }
+    }
+
+    fn decode(&self, _context: &RealVectorContext) -> Self {
+        self.clone()
+    }
+
+    /// Perturb every value with the per gene rate of the context
+    fn mutate(&mut self, context: &RealVectorContext, rng: &mut impl Rng) {
+        for (x, &bounds) in self.values.iter_mut().zip(&context.bounds) {
+            if !rng.gen_bool(context.gene_rate) {
+                continue;
+            }
+
+            let range = bounds.1 - bounds.0;
+            let mutated = match context.mutation {
+                RealMutation::Gaussian => *x + standard_normal(rng) * context.sigma * range,
+                RealMutation::Polynomial => {
+                    *x + polynomial_delta(context.polynomial_eta, rng) * range
+                }
+            };
+
+            *x = repair(mutated, bounds, context.bound_handling, rng);
+        }
+    }
+
+    fn crossover(&self, other: &Self, context: &RealVectorContext, rng: &mut impl Rng) -> Self {
+        // Arithmetic crossover blends the whole vectors with the same weight
+        let weight: f64 = rng.gen();
+        let values = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .zip(&context.bounds)
+            .map(|((&a, &b), &bounds)| {
+                let child = match context.crossover {
+                    // Each value is recombined with probability 0.5, as proposed by Deb
+                    RealCrossover::SimulatedBinary if rng.gen_bool(0.5) => {
+                        simulated_binary(a, b, context.sbx_eta, rng)
+                    }
+                    RealCrossover::SimulatedBinary => a,
+                    RealCrossover::BlendAlpha => {
+                        let distance = (a - b).abs() * context.alpha;
+                        let (low, high) = (a.min(b) - distance, a.max(b) + distance);
+                        if low < high {
+                            rng.gen_range(low..high)
+                        } else {
+                            a
+                        }
+                    }
+                    RealCrossover::Arithmetic => weight * a + (1.0 - weight) * b,
+                };
+
+                repair(child, bounds, context.bound_handling, rng)
+            })
+            .collect();
+
+        RealVector { values }
+    }
+}
+
+impl Phenotype<RealVectorContext> for RealVector {
+    fn fitness(&self, context: &RealVectorContext) -> f64 {
+        (context.fitness)(&self.values)
+    }
+}
+
+#[test]
+fn test_bound_handling() {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let bounds = (-1.0, 1.0);
+
+    assert_eq!(repair(1.5, bounds, BoundHandling::Clamp, &mut rng), 1.0);
+    assert_eq!(repair(1.5, bounds, BoundHandling::Reflect, &mut rng), 0.5);
+    assert_eq!(
+        repair(-1.25, bounds, BoundHandling::Reflect, &mut rng),
+        -0.75
+    );
+    assert_eq!(repair(3.5, bounds, BoundHandling::Reflect, &mut rng), -0.5);
+    assert_eq!(repair(0.3, bounds, BoundHandling::Resample, &mut rng), 0.3);
+    assert!((-1.0..=1.0).contains(&repair(7.0, bounds, BoundHandling::Resample, &mut rng)));
+}
+
+#[test]
+fn test_sphere_is_minimized() {
+    use crate::ea::{GeneticAlgorithm, ParentSelection, Population};
+
+    let sphere = |x: &[f64]| x.iter().map(|v| v * v).sum::<f64>();
+
+    for crossover in &[
+        RealCrossover::SimulatedBinary,
+        RealCrossover::BlendAlpha,
+        RealCrossover::Arithmetic,
+    ] {
+        for mutation in &[RealMutation::Gaussian, RealMutation::Polynomial] {
+            let context = RealVectorContext::new(vec![(-5.0, 5.0); 5], sphere)
+                .crossover(*crossover)
+                .mutation(*mutation);
+            let result = GeneticAlgorithm::<RealVector>::builder()
+                .seed(3)
+                .minimize(true)
+                .target_fitness(0.0)
+                .parent_selection(ParentSelection::TournamentSelection)
+                .max_generations(200)
+                .build(context)
+                .evolve();
+
+            assert!(result.best.fitness < 0.5, "{:?} {:?}", crossover, mutation);
+            assert!(result.best.genotype.values.iter().all(|v| v.abs() <= 5.0));
+        }
+    }
+}
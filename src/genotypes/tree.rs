@@ -0,0 +1,512 @@
+/// Expression trees for tree-based genetic programming
+use crate::ea::{Genotype, Phenotype};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use structopt::clap::arg_enum;
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Functions available to the inner nodes of an expression tree
+    #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+    pub enum Function {
+        Add,
+        Sub,
+        Mul,
+        Div,
+        Sin,
+        Cos,
+        Exp,
+        Log,
+    }
+}
+
+// These are wrapped in arg_enum since we are constructing these directly from StructOpt
+arg_enum! {
+    /// Available mutation operators for expression trees
+    #[derive(Copy, Clone, Debug)]
+    pub enum TreeMutation {
+        Point,
+        Subtree,
+        Hoist,
+    }
+}
+
+impl Function {
+    /// Number of arguments
+    pub fn arity(self) -> usize {
+        match self {
+            Function::Add | Function::Sub | Function::Mul | Function::Div => 2,
+            Function::Sin | Function::Cos | Function::Exp | Function::Log => 1,
+        }
+    }
+
+    /// Apply the function to its arguments. Division, exponentiation and logarithms are
+    /// protected so every expression has a finite value where possible.
+    pub fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Function::Add => a + b,
+            Function::Sub => a - b,
+            Function::Mul => a * b,
+            Function::Div if b.abs() < 1e-9 => 1.0,
+            Function::Div => a / b,
+            Function::Sin => a.sin(),
+            Function::Cos => a.cos(),
+            Function::Exp => a.min(100.0).exp(),
+            Function::Log if a.abs() < 1e-9 => 0.0,
+            Function::Log => a.abs().ln(),
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Function::Add => "+",
+            Function::Sub => "-",
+            Function::Mul => "*",
+            Function::Div => "/",
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Exp => "exp",
+            Function::Log => "log",
+        }
+    }
+}
+
+/// A node of an expression tree
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Node {
+    Function(Function),
+    /// Input variable with the given index
+    Variable(usize),
+    Constant(f64),
+}
+
+impl Node {
+    fn arity(self) -> usize {
+        match self {
+            Node::Function(function) => function.arity(),
+            Node::Variable(_) | Node::Constant(_) => 0,
+        }
+    }
+}
+
+/// An expression tree stored as its nodes in prefix order
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct ExpressionTree {
+    nodes: Vec<Node>,
+}
+
+impl ExpressionTree {
+    /// Tree of the given nodes in prefix order
+    pub fn from_nodes(nodes: Vec<Node>) -> Self {
+        assert_eq!(
+            subtree_end(&nodes, 0),
+            nodes.len(),
+            "nodes do not form a single tree"
+        );
+        ExpressionTree { nodes }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Length of the longest path from the root to a leaf, 0 for a single terminal
+    pub fn depth(&self) -> usize {
+        self.depths().into_iter().max().unwrap_or(0)
+    }
+
+    /// Depth of every node
+    fn depths(&self) -> Vec<usize> {
+        let mut depths = Vec::with_capacity(self.nodes.len());
+        // Remaining arguments of the functions on the path to the current node
+        let mut open: Vec<usize> = Vec::new();
+
+        for node in &self.nodes {
+            depths.push(open.len());
+
+            if let Some(remaining) = open.last_mut() {
+                *remaining -= 1;
+            }
+            if node.arity() > 0 {
+                open.push(node.arity());
+            }
+            while open.last() == Some(&0) {
+                open.pop();
+            }
+        }
+
+        depths
+    }
+
+    /// Value of the expression for the given variables
+    pub fn evaluate(&self, variables: &[f64]) -> f64 {
+        let mut stack: Vec<f64> = Vec::with_capacity(self.nodes.len());
+
+        // Evaluating the prefix order backwards leaves the first argument on top of the stack
+        for node in self.nodes.iter().rev() {
+            let value = match *node {
+                Node::Variable(i) => variables[i],
+                Node::Constant(c) => c,
+                Node::Function(function) => {
+                    let a = stack.pop().unwrap();
+                    let b = if function.arity() == 2 {
+                        stack.pop().unwrap()
+                    } else {
+                        0.0
+                    };
+                    function.apply(a, b)
+                }
+            };
+            stack.push(value);
+        }
+
+        stack.pop().unwrap_or(0.0)
+    }
+
+    /// Pick a node to cross over or mutate at, preferring inner nodes 90% of the time as
+    /// proposed by Koza
+    fn random_point(&self, rng: &mut impl Rng) -> usize {
+        let functions: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].arity() > 0)
+            .collect();
+
+        if !functions.is_empty() && rng.gen_bool(0.9) {
+            *functions.choose(rng).unwrap()
+        } else {
+            let terminals: Vec<usize> = (0..self.nodes.len())
+                .filter(|&i| self.nodes[i].arity() == 0)
+                .collect();
+            *terminals.choose(rng).unwrap()
+        }
+    }
+
+    /// Copy of self with the subtree at the given node replaced by the given nodes
+    fn replace(&self, at: usize, subtree: &[Node]) -> Self {
+        let end = subtree_end(&self.nodes, at);
+        let mut nodes = Vec::with_capacity(self.nodes.len() - (end - at) + subtree.len());
+
+        nodes.extend_from_slice(&self.nodes[..at]);
+        nodes.extend_from_slice(subtree);
+        nodes.extend_from_slice(&self.nodes[end..]);
+
+        ExpressionTree { nodes }
+    }
+
+    /// Nodes of the subtree starting at the given node
+    fn subtree(&self, at: usize) -> &[Node] {
+        &self.nodes[at..subtree_end(&self.nodes, at)]
+    }
+
+    fn write_node(&self, f: &mut fmt::Formatter, i: usize) -> Result<usize, fmt::Error> {
+        match self.nodes[i] {
+            Node::Variable(v) => {
+                write!(f, "x{}", v)?;
+                Ok(i + 1)
+            }
+            Node::Constant(c) => {
+                write!(f, "{:.3}", c)?;
+                Ok(i + 1)
+            }
+            Node::Function(function) if function.arity() == 2 => {
+                write!(f, "(")?;
+                let next = self.write_node(f, i + 1)?;
+                write!(f, " {} ", function.symbol())?;
+                let next = self.write_node(f, next)?;
+                write!(f, ")")?;
+                Ok(next)
+            }
+            Node::Function(function) => {
+                write!(f, "{}(", function.symbol())?;
+                let next = self.write_node(f, i + 1)?;
+                write!(f, ")")?;
+                Ok(next)
+            }
+        }
+    }
+}
+
+/// Index just past the subtree starting at the given node
+fn subtree_end(nodes: &[Node], start: usize) -> usize {
+    let mut needed = 1;
+
+    for (i, node) in nodes.iter().enumerate().skip(start) {
+        needed = needed + node.arity() - 1;
+
+        if needed == 0 {
+            return i + 1;
+        }
+    }
+
+    panic!("incomplete subtree at {}", start);
+}
+
+impl fmt::Display for ExpressionTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_node(f, 0).map(|_| ())
+    }
+}
+
+/// Fitness function of an expression tree
+pub type TreeFitness = dyn Fn(&ExpressionTree) -> f64 + Send + Sync;
+
+/// Function and terminal sets, size limits and fitness of a genetic programming problem
+pub struct TreeContext {
+    /// Functions of the inner nodes
+    pub functions: Vec<Function>,
+    /// Number of input variables available as terminals
+    pub variables: usize,
+    /// Range of the random constants available as terminals, if any
+    pub constants: Option<(f64, f64)>,
+    /// Smallest and largest depth of new trees
+    pub init_depth: (usize, usize),
+    /// Offspring deeper than this are replaced by a copy of their parent
+    pub max_depth: usize,
+    /// Largest depth of the new subtrees grown by subtree mutation
+    pub mutation_depth: usize,
+    /// Mutation operator
+    pub mutation: TreeMutation,
+    /// Fitness of a tree
+    pub fitness: Box<TreeFitness>,
+}
+
+impl TreeContext {
+    /// Trees over the given functions and variables evaluated by the fitness function, with
+    /// constants in [-1, 1], initial depths from 2 to 6 and Koza's depth limit of 17
+    pub fn new<F>(functions: Vec<Function>, variables: usize, fitness: F) -> Self
+    where
+        F: Fn(&ExpressionTree) -> f64 + Send + Sync + 'static,
+    {
+        TreeContext {
+            functions,
+            variables,
+            constants: Some((-1.0, 1.0)),
+            init_depth: (2, 6),
+            max_depth: 17,
+            mutation_depth: 4,
+            mutation: TreeMutation::Subtree,
+            fitness: Box::new(fitness),
+        }
+    }
+
+    /// Range of the random constants, or None for trees of variables only. Panics if there are
+    /// no variables either, since trees need terminals.
+    pub fn constants(mut self, constants: Option<(f64, f64)>) -> Self {
+        assert!(
+            self.variables > 0 || constants.is_some(),
+            "Expression trees need variables or constants as terminals"
+        );
+        self.constants = constants;
+        self
+    }
+
+    /// Depth range of new trees, limited to the maximum depth
+    pub fn init_depth(mut self, min: usize, max: usize) -> Self {
+        let max = max.max(min).min(self.max_depth);
+        self.init_depth = (min.min(max), max);
+        self
+    }
+
+    /// Depth limit of all trees, lowering the depth range of new trees if needed
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        let (min, max) = self.init_depth;
+        self.max_depth = max_depth;
+        self.init_depth(min, max)
+    }
+
+    pub fn mutation(mut self, mutation: TreeMutation) -> Self {
+        self.mutation = mutation;
+        self
+    }
+
+    /// A random variable or constant
+    fn terminal(&self, rng: &mut impl Rng) -> Node {
+        let choices = self.variables + self.constants.is_some() as usize;
+        let i = rng.gen_range(0..choices.max(1));
+
+        match self.constants {
+            Some((low, high)) if i == self.variables => Node::Constant(rng.gen_range(low..=high)),
+            _ => Node::Variable(i),
+        }
+    }
+
+    /// Append a random tree of at most the given depth. Full trees only have terminals at the
+    /// maximum depth, grown trees pick from all functions and terminals at every node above it.
+    fn grow(&self, depth: usize, full: bool, nodes: &mut Vec<Node>, rng: &mut impl Rng) {
+        let terminals = self.variables + self.constants.is_some() as usize;
+        let p_function = self.functions.len() as f64 / (self.functions.len() + terminals) as f64;
+
+        if depth == 0 || self.functions.is_empty() || (!full && !rng.gen_bool(p_function)) {
+            nodes.push(self.terminal(rng));
+            return;
+        }
+
+        let function = *self.functions.choose(rng).unwrap();
+        nodes.push(Node::Function(function));
+        for _ in 0..function.arity() {
+            self.grow(depth - 1, full, nodes, rng);
+        }
+    }
+
+    /// Offspring within the depth limit, or a copy of the parent
+    fn limit(&self, offspring: ExpressionTree, parent: &ExpressionTree) -> ExpressionTree {
+        if offspring.depth() > self.max_depth {
+            parent.clone()
+        } else {
+            offspring
+        }
+    }
+}
+
+impl Genotype for ExpressionTree {
+    type Context = TreeContext;
+    type Phenotype = Self;
+
+    /// Ramped half-and-half: a depth from the initial range and either the full or the grow
+    /// method are picked at random for every tree
+    fn new(rng: &mut impl Rng, context: &TreeContext) -> Self {
+        let (min, max) = context.init_depth;
+        let depth = rng.gen_range(min..=max);
+        let mut nodes = Vec::new();
+
+        context.grow(depth, rng.gen_bool(0.5), &mut nodes, rng);
+
+        ExpressionTree { nodes }
+    }
+
+    fn decode(&self, _context: &TreeContext) -> Self {
+        self.clone()
+    }
+
+    fn mutate(&mut self, context: &TreeContext, rng: &mut impl Rng) {
+        let at = self.random_point(rng);
+
+        *self = match context.mutation {
+            // Replace a single node with another one of the same arity
+            TreeMutation::Point => {
+                let mut mutated = self.clone();
+                mutated.nodes[at] = match self.nodes[at] {
+                    Node::Function(function) => {
+                        let same_arity: Vec<Function> = context
+                            .functions
+                            .iter()
+                            .copied()
+                            .filter(|f| f.arity() == function.arity())
+                            .collect();
+                        Node::Function(*same_arity.choose(rng).unwrap_or(&function))
+                    }
+                    _ => context.terminal(rng),
+                };
+                mutated
+            }
+            TreeMutation::Subtree => {
+                let mut subtree = Vec::new();
+                let depth = rng.gen_range(0..=context.mutation_depth);
+                context.grow(depth, false, &mut subtree, rng);
+                context.limit(self.replace(at, &subtree), self)
+            }
+            // Replace the tree with one of its subtrees, which can only make it smaller
+            TreeMutation::Hoist => ExpressionTree {
+                nodes: self.subtree(at).to_vec(),
+            },
+        };
+    }
+
+    /// Subtree crossover, replacing a random subtree of self with one from other
+    fn crossover(&self, other: &Self, context: &TreeContext, rng: &mut impl Rng) -> Self {
+        let at = self.random_point(rng);
+        let subtree = other.subtree(other.random_point(rng));
+
+        context.limit(self.replace(at, subtree), self)
+    }
+
+    fn size(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl Phenotype<TreeContext> for ExpressionTree {
+    fn fitness(&self, context: &TreeContext) -> f64 {
+        (context.fitness)(self)
+    }
+}
+
+#[test]
+fn test_tree_structure() {
+    use Function::*;
+
+    // (x0 * x0) + sin(1.5)
+    let tree = ExpressionTree::from_nodes(vec![
+        Node::Function(Add),
+        Node::Function(Mul),
+        Node::Variable(0),
+        Node::Variable(0),
+        Node::Function(Sin),
+        Node::Constant(1.5),
+    ]);
+
+    assert_eq!(tree.depth(), 2);
+    assert_eq!(tree.depths(), [0, 1, 2, 2, 1, 2]);
+    assert_eq!(subtree_end(tree.nodes(), 1), 4);
+    assert_eq!(tree.to_string(), "((x0 * x0) + sin(1.500))");
+    assert_eq!(tree.evaluate(&[3.0]), 9.0 + 1.5f64.sin());
+    assert_eq!(
+        ExpressionTree::from_nodes(vec![
+            Node::Function(Div),
+            Node::Variable(0),
+            Node::Constant(0.0)
+        ])
+        .evaluate(&[2.0]),
+        1.0
+    );
+}
+
+#[test]
+fn test_operators_respect_depth_limit() {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2);
+    let functions = Function::variants()
+        .iter()
+        .map(|f| f.parse().unwrap())
+        .collect();
+    let mut context = TreeContext::new(functions, 2, |t| t.len() as f64).max_depth(8);
+
+    // New trees stay within the limit even when it is below the initial depths
+    let shallow = TreeContext::new(vec![Function::Add], 1, |t| t.len() as f64).max_depth(1);
+    assert_eq!(shallow.init_depth, (1, 1));
+    assert!((0..20).all(|_| ExpressionTree::new(&mut rng, &shallow).depth() <= 1));
+
+    for mutation in &[
+        TreeMutation::Point,
+        TreeMutation::Subtree,
+        TreeMutation::Hoist,
+    ] {
+        context.mutation = *mutation;
+        let mut trees: Vec<ExpressionTree> = (0..20)
+            .map(|_| ExpressionTree::new(&mut rng, &context))
+            .collect();
+
+        for _ in 0..50 {
+            for i in 0..trees.len() {
+                let mut child = trees[i].crossover(&trees[(i + 1) % 20], &context, &mut rng);
+                child.mutate(&context, &mut rng);
+
+                // Every operator keeps a single well formed tree
+                assert_eq!(subtree_end(child.nodes(), 0), child.len());
+                assert!(child.depth() <= 8);
+                trees[i] = child;
+            }
+        }
+    }
+}
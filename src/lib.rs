@@ -1,4 +1,5 @@
 pub mod ea;
+pub mod genotypes;
 pub mod opt;
 pub mod problems;
 
@@ -1,27 +1,201 @@
-use rust_ga::ea::Population;
+use rust_ga::ea::{check_reference_point, MoeaD, MultiObjective, MultiObjectiveAlgorithm};
+use rust_ga::ea::{checkpoint_config, Config, Genotype, Population, RunResult};
+use rust_ga::ea::{IslandModel, StandardPopulation, StatsWriter};
+use rust_ga::ea::{Nsga2, Spea2};
 use rust_ga::problems;
 use rust_ga::Options;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use structopt::StructOpt;
 
+/// The statistics writer requested on the command line, if any
+fn stats_writer(options: &Options) -> Option<StatsWriter<BufWriter<File>>> {
+    let path = options.stats_out.as_ref()?;
+
+    // A resumed run continues the statistics of the run it was saved from
+    let writer = if options.resume.is_some() {
+        StatsWriter::append(path, options.stats_format)
+    } else {
+        StatsWriter::create(path, options.stats_format)
+    };
+
+    match writer {
+        Ok(writer) => Some(writer),
+        Err(e) => {
+            eprintln!("Failed to create {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Attach the observers requested on the command line and evolve the population
+fn run<T>(mut population: StandardPopulation<T>, options: &Options) -> RunResult<T>
+where
+    T: Genotype + Display + PartialOrd,
+{
+    if let Some(writer) = stats_writer(options) {
+        population.add_observer(Box::new(writer));
+    }
+
+    if options.islands > 1 {
+        if options.checkpoint.is_some() || options.resume.is_some() {
+            eprintln!("Checkpoints are not supported with islands");
+            process::exit(1);
+        }
+
+        return IslandModel::from(population).evolve();
+    }
+
+    if let Some(path) = &options.resume {
+        if let Err(e) = population.load_checkpoint(path) {
+            eprintln!("Failed to resume from {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+
+    // Keep saving to the resumed checkpoint unless told otherwise
+    if let Some(path) = options.checkpoint.as_ref().or(options.resume.as_ref()) {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let flag = interrupted.clone();
+
+        if let Err(e) = ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst)) {
+            eprintln!("Failed to install interrupt handler: {}", e);
+        }
+        population.set_checkpoint(path.clone(), options.checkpoint_interval);
+        population.set_interrupt(interrupted);
+    }
+
+    population.evolve()
+}
+
+/// Evolve a multi-objective population with the engine chosen on the command line and print
+/// the objectives of its final Pareto front
+fn run_pareto<T>(mut population: StandardPopulation<T>, options: &Options) -> RunResult<T>
+where
+    T: Genotype + Display + PartialOrd,
+    T::Phenotype: MultiObjective<T::Context>,
+{
+    if options.islands > 1 || options.checkpoint.is_some() || options.resume.is_some() {
+        eprintln!("Islands and checkpoints are not supported with multiple objectives");
+        process::exit(1);
+    }
+    if let Err(e) = check_reference_point(&population) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+    if let Some(writer) = stats_writer(options) {
+        population.add_observer(Box::new(writer));
+    }
+
+    let result = match options.multi_objective {
+        MultiObjectiveAlgorithm::Nsga2 => Nsga2::from(population).evolve(),
+        MultiObjectiveAlgorithm::Spea2 => Spea2::from(population).evolve(),
+        MultiObjectiveAlgorithm::MoeaD => MoeaD::from(population).evolve(),
+    };
+
+    println!("Objectives of the Pareto front:");
+    for individual in &result.pareto_front {
+        let objectives: Vec<String> = individual
+            .objectives
+            .iter()
+            .map(|o| format!("{:.3}", o))
+            .collect();
+        println!("  {}", objectives.join(", "));
+    }
+
+    result
+}
+
 fn main() {
     let name = option_env!("CARGO_PKG_NAME").unwrap_or("unknown");
     let version = option_env!("CARGO_PKG_VERSION").unwrap_or("unknown");
-    let args = Options::from_args();
+    let mut args = Options::from_args();
 
     println!("{} v{}", name, version);
 
+    // Random cities are generated from the seed, so a resumable run always needs one
+    if let Some(path) = &args.resume {
+        match checkpoint_config(path) {
+            Ok(config) => args.seed = config.seed,
+            Err(e) => {
+                eprintln!("Failed to read checkpoint {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    } else if args.checkpoint.is_some() && args.seed.is_none() {
+        args.seed = Some(rand::random());
+    }
+
+    if let Err(e) = Config::from(&args).validate() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+
     match args.problem {
         problems::Problem::TravelingSalesman => {
-            let (mut pop, distances) = problems::create_tsp(args);
+            let (pop, optimum) = match problems::create_tsp(args.clone()) {
+                Ok(tsp) => tsp,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            };
 
-            for p in pop.iter_mut() {
-                p.genotype.distances = Some(&distances);
-            }
+            let result = run(pop, &args);
 
-            pop.evolve();
+            if let Some(optimum) = optimum {
+                let gap = (result.best.fitness - optimum) / optimum * 100.0;
+                println!("Optimal tour length {:.0}, gap {:.2}%", optimum, gap);
+            }
         }
-        _ => {
-            problems::create_nqueens(args).evolve();
+        problems::Problem::BiObjectiveTsp => {
+            match problems::create_bi_objective_tsp(args.clone()) {
+                Ok(pop) => {
+                    run_pareto(pop, &args);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        problems::Problem::OneMax => match problems::create_onemax(args.clone()) {
+            Ok(pop) => {
+                run(pop, &args);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        problems::Problem::NQueens => {
+            run(problems::create_nqueens(args.clone()), &args);
+        }
+        problems::Problem::SymbolicRegression => {
+            match problems::create_symbolic_regression(args.clone()) {
+                Ok(pop) => {
+                    run(pop, &args);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        problems::Problem::GrammaticalEvolution => {
+            match problems::create_grammatical_evolution(args.clone()) {
+                Ok(pop) => {
+                    run(pop, &args);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
         }
     }
 }
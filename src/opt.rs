@@ -1,11 +1,23 @@
 /// Options
-use crate::ea::PopulationModel;
-use crate::ea::{ParentSelection, SurvivorSelection};
+use crate::ea::{Aggregation, MultiObjectiveAlgorithm};
+use crate::ea::{CellularUpdate, Config, LocalSearch, Neighbourhood, PopulationModel, StatsFormat};
+use crate::ea::{MigrationPolicy, MigrationTopology, ParentSelection, SurvivorSelection};
+use crate::genotypes::{BitCrossover, Function, PermutationCrossover};
+use crate::genotypes::{PermutationMutation, TreeMutation};
 use crate::problems::Problem;
+use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 
+/// Parse a number of seconds, rejecting negative, infinite and NaN values
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("{}", e))?;
+
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("{} seconds: {}", value, e))
+}
+
 /// Command line interface
-#[derive(Copy, Clone, Debug, StructOpt)]
+#[derive(Clone, Debug, StructOpt)]
 #[structopt(name = "rust-ga", about = "Simple genetic algorithm")]
 pub struct Options {
     /// Which problem to solve
@@ -21,6 +33,15 @@ pub struct Options {
     #[structopt(long = "problem-size", default_value = "16")]
     pub problem_size: usize,
 
+    /// TSPLIB instance (.tsp) to solve instead of randomly generated cities
+    #[structopt(long = "instance", parse(from_os_str))]
+    pub instance: Option<PathBuf>,
+
+    /// Known optimal tour (.opt.tour) used to report the gap to the optimum. Defaults to a
+    /// .opt.tour file next to the instance, if there is one.
+    #[structopt(long = "optimal-tour", parse(from_os_str))]
+    pub optimal_tour: Option<PathBuf>,
+
     /// Population size
     #[structopt(short = "p", long = "population", default_value = "50")]
     pub population: usize,
@@ -33,6 +54,22 @@ pub struct Options {
     #[structopt(short = "t", long = "target", default_value = "1.0")]
     pub target_fitness: f64,
 
+    /// Stop after this many seconds
+    #[structopt(long = "time-limit", parse(try_from_str = parse_seconds))]
+    pub time_limit: Option<Duration>,
+
+    /// Stop after this many fitness evaluations
+    #[structopt(long = "max-evaluations")]
+    pub max_evaluations: Option<u64>,
+
+    /// Stop when the best fitness has not improved for this many generations
+    #[structopt(long = "stagnation")]
+    pub stagnation: Option<u32>,
+
+    /// Stop when the fraction of distinct individuals in the population drops below this value
+    #[structopt(long = "min-diversity")]
+    pub min_diversity: Option<f64>,
+
     /// Mutation rate
     #[structopt(short = "m", long = "mutation", default_value = "0.1")]
     pub mutation_rate: f64,
@@ -41,6 +78,79 @@ pub struct Options {
     #[structopt(short = "c", long = "crossover", default_value = "0.5")]
     pub crossover_rate: f64,
 
+    /// Crossover operator for permutation problems like the traveling salesman
+    #[structopt(
+        long = "permutation-crossover",
+        possible_values = &PermutationCrossover::variants(),
+        case_insensitive = true,
+        default_value = "Order"
+    )]
+    pub permutation_crossover: PermutationCrossover,
+
+    /// Mutation operator for permutation problems, defaults to a mix of swaps, shifts and new
+    /// random paths for the traveling salesman
+    #[structopt(
+        long = "permutation-mutation",
+        possible_values = &PermutationMutation::variants(),
+        case_insensitive = true
+    )]
+    pub permutation_mutation: Option<PermutationMutation>,
+
+    /// Crossover operator for bit string problems like OneMax
+    #[structopt(
+        long = "bit-crossover",
+        possible_values = &BitCrossover::variants(),
+        case_insensitive = true,
+        default_value = "OnePoint"
+    )]
+    pub bit_crossover: BitCrossover,
+
+    /// Probability of flipping each bit when mutating a bit string, defaults to one over the
+    /// number of bits
+    #[structopt(long = "gene-rate")]
+    pub gene_rate: Option<f64>,
+
+    /// CSV file of x,y samples to fit with symbolic regression or grammatical evolution
+    /// instead of Koza's quartic polynomial
+    #[structopt(long = "samples", parse(from_os_str))]
+    pub samples: Option<PathBuf>,
+
+    /// Functions available to expressions in symbolic regression
+    #[structopt(
+        long = "functions",
+        possible_values = &Function::variants(),
+        case_insensitive = true,
+        use_delimiter = true,
+        default_value = "Add,Sub,Mul,Div"
+    )]
+    pub functions: Vec<Function>,
+
+    /// Deepest expression tree allowed in symbolic regression, deeper offspring are discarded
+    #[structopt(long = "max-depth", default_value = "17")]
+    pub max_depth: usize,
+
+    /// Mutation operator for the expression trees of symbolic regression
+    #[structopt(
+        long = "tree-mutation",
+        possible_values = &TreeMutation::variants(),
+        case_insensitive = true,
+        default_value = "Subtree"
+    )]
+    pub tree_mutation: TreeMutation,
+
+    /// BNF grammar file producing the expressions of grammatical evolution, defaults to
+    /// arithmetic over x
+    #[structopt(long = "grammar", parse(from_os_str))]
+    pub grammar: Option<PathBuf>,
+
+    /// Number of times the codons of grammatical evolution may be reused when mapping
+    #[structopt(long = "max-wraps", default_value = "2")]
+    pub max_wraps: usize,
+
+    /// Whether or not to keep the best individual, on by default except in the (μ,λ) model
+    #[structopt(long = "elitism", conflicts_with = "no-elitism")]
+    pub elitism: bool,
+
     /// Whether or not to turn off elitism
     #[structopt(long = "no-elitism")]
     pub no_elitism: bool,
@@ -58,6 +168,14 @@ pub struct Options {
     )]
     pub parent_selection: ParentSelection,
 
+    /// Number of individuals competing in each tournament when using tournament selection
+    #[structopt(long = "tournament-size", default_value = "3")]
+    pub tournament_size: usize,
+
+    /// Selection pressure between 1.0 and 2.0 when using rank selection
+    #[structopt(long = "selection-pressure", default_value = "1.5")]
+    pub selection_pressure: f64,
+
     /// Survivor selection stragegy
     #[structopt(
         long = "survivor-selection",
@@ -76,7 +194,205 @@ pub struct Options {
     )]
     pub population_model: PopulationModel,
 
+    /// Local search applied to offspring before evaluation, turning the GA into a memetic
+    /// algorithm for problems that support it
+    #[structopt(
+        long = "local-search",
+        possible_values = &LocalSearch::variants(),
+        case_insensitive = true,
+        default_value = "None"
+    )]
+    pub local_search: LocalSearch,
+
+    /// Number of offspring replacing existing individuals each step of a steady state population
+    #[structopt(long = "replacement-count", default_value = "2")]
+    pub replacement_count: usize,
+
+    /// Number of offspring (λ) produced from μ parents in the (μ+λ) and (μ,λ) population
+    /// models. Defaults to the population size.
+    #[structopt(long = "offspring")]
+    pub offspring: Option<usize>,
+
+    /// Seed for the random number generator, making runs with the same options reproducible
+    #[structopt(long = "seed")]
+    pub seed: Option<u64>,
+
+    /// Width of the grid of a cellular population, 0 picks the most square grid
+    #[structopt(long = "grid-width", default_value = "0")]
+    pub grid_width: usize,
+
+    /// Shape of the neighbourhood of a cell in a cellular population
+    #[structopt(
+        long = "neighbourhood",
+        possible_values = &Neighbourhood::variants(),
+        case_insensitive = true,
+        default_value = "VonNeumann"
+    )]
+    pub neighbourhood: Neighbourhood,
+
+    /// Distance from a cell to the edge of its neighbourhood in a cellular population
+    #[structopt(long = "neighbourhood-radius", default_value = "1")]
+    pub neighbourhood_radius: usize,
+
+    /// Whether all cells of a cellular population are replaced at once, or one at a time in a
+    /// random order
+    #[structopt(
+        long = "cellular-update",
+        possible_values = &CellularUpdate::variants(),
+        case_insensitive = true,
+        default_value = "Synchronous"
+    )]
+    pub cellular_update: CellularUpdate,
+
+    /// Number of populations evolving side by side in an island model, 1 disables it
+    #[structopt(long = "islands", default_value = "1")]
+    pub islands: usize,
+
+    /// Number of generations between migrations of individuals between islands
+    #[structopt(long = "migration-interval", default_value = "20")]
+    pub migration_interval: u32,
+
+    /// Number of individuals each island sends per migration
+    #[structopt(long = "migrants", default_value = "2")]
+    pub migrants: usize,
+
+    /// Which islands send migrants to which
+    #[structopt(
+        long = "migration-topology",
+        possible_values = &MigrationTopology::variants(),
+        case_insensitive = true,
+        default_value = "Ring"
+    )]
+    pub migration_topology: MigrationTopology,
+
+    /// Which individuals leave an island
+    #[structopt(
+        long = "migration-policy",
+        possible_values = &MigrationPolicy::variants(),
+        case_insensitive = true,
+        default_value = "Best"
+    )]
+    pub migration_policy: MigrationPolicy,
+
+    /// Number of threads evaluating fitness when built with the parallel feature, 0 means one
+    /// per CPU core
+    #[structopt(long = "threads", default_value = "0")]
+    pub threads: usize,
+
+    /// Engine evolving multi-objective problems
+    #[structopt(
+        long = "multi-objective",
+        possible_values = &MultiObjectiveAlgorithm::variants(),
+        case_insensitive = true,
+        default_value = "Nsga2"
+    )]
+    pub multi_objective: MultiObjectiveAlgorithm,
+
+    /// Size of the archive of non-dominated individuals in SPEA2, defaults to the population
+    /// size
+    #[structopt(long = "archive-size")]
+    pub archive_size: Option<usize>,
+
+    /// How MOEA/D scalarizes the objectives of each subproblem
+    #[structopt(
+        long = "aggregation",
+        possible_values = &Aggregation::variants(),
+        case_insensitive = true,
+        default_value = "Tchebycheff"
+    )]
+    pub aggregation: Aggregation,
+
+    /// Penalty on the distance from the weight vector in PBI aggregation
+    #[structopt(long = "pbi-penalty", default_value = "5.0")]
+    pub pbi_penalty: f64,
+
+    /// Number of closest weight vectors whose subproblems share offspring in MOEA/D
+    #[structopt(long = "weight-neighbours", default_value = "20")]
+    pub weight_neighbours: usize,
+
+    /// Point the hypervolume of the Pareto front is measured from, like 5000,5000. Defaults to
+    /// just beyond the worst value of every objective on the final front, so hypervolumes of
+    /// different runs are only comparable with a given point.
+    #[structopt(long = "reference-point", use_delimiter = true)]
+    pub reference_point: Vec<f64>,
+
+    /// File to write the statistics of every generation to
+    #[structopt(long = "stats-out", parse(from_os_str))]
+    pub stats_out: Option<PathBuf>,
+
+    /// Format of the statistics file
+    #[structopt(
+        long = "stats-format",
+        possible_values = &StatsFormat::variants(),
+        case_insensitive = true,
+        default_value = "Csv"
+    )]
+    pub stats_format: StatsFormat,
+
+    /// File to save checkpoints of the evolution to, both periodically and on Ctrl-C
+    #[structopt(long = "checkpoint", parse(from_os_str))]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Number of generations between checkpoints, 0 only saves when interrupted
+    #[structopt(long = "checkpoint-interval", default_value = "100")]
+    pub checkpoint_interval: u32,
+
+    /// Continue the evolution saved in a checkpoint file. The problem options must be the same
+    /// as for the original run, the evolution settings are taken from the checkpoint.
+    #[structopt(long = "resume", parse(from_os_str))]
+    pub resume: Option<PathBuf>,
+
     /// Activate debug mode
     #[structopt(short, long)]
     pub debug: bool,
 }
+
+/// Engine settings from the command line options
+impl From<&Options> for Config {
+    fn from(options: &Options) -> Self {
+        Config {
+            population: options.population,
+            max_generations: options.max_generations,
+            target_fitness: options.target_fitness,
+            time_limit: options.time_limit,
+            max_evaluations: options.max_evaluations,
+            stagnation: options.stagnation,
+            min_diversity: options.min_diversity,
+            termination: None,
+            mutation_rate: options.mutation_rate,
+            crossover_rate: options.crossover_rate,
+            elitism: if options.elitism {
+                Some(true)
+            } else if options.no_elitism {
+                Some(false)
+            } else {
+                None
+            },
+            minimize: options.minimize,
+            parent_selection: options.parent_selection,
+            tournament_size: options.tournament_size,
+            selection_pressure: options.selection_pressure,
+            survivor_selection: options.survivor_selection,
+            population_model: options.population_model,
+            replacement_count: options.replacement_count,
+            offspring: options.offspring,
+            local_search: options.local_search,
+            seed: options.seed,
+            grid_width: options.grid_width,
+            neighbourhood: options.neighbourhood,
+            neighbourhood_radius: options.neighbourhood_radius,
+            cellular_update: options.cellular_update,
+            islands: options.islands,
+            migration_interval: options.migration_interval,
+            migrants: options.migrants,
+            migration_topology: options.migration_topology,
+            migration_policy: options.migration_policy,
+            threads: options.threads,
+            archive_size: options.archive_size,
+            aggregation: options.aggregation,
+            pbi_penalty: options.pbi_penalty,
+            weight_neighbours: options.weight_neighbours,
+            reference_point: Some(options.reference_point.clone()).filter(|p| !p.is_empty()),
+        }
+    }
+}
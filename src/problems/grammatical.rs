@@ -0,0 +1,213 @@
+/// Symbolic regression with grammatical evolution, evaluating the arithmetic expressions
+/// produced by a grammar
+use super::regression::mean_squared_error;
+use crate::genotypes::{ExpressionTree, Function, Grammar, GrammarContext, Node};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Grammar used when none is given, producing expressions of x over + - * /
+pub const DEFAULT_GRAMMAR: &str = "
+<expr> ::= <expr><op><expr> | (<expr><op><expr>) | <var>
+<op>   ::= + | - | * | /
+<var>  ::= x | 1.0
+";
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Nodes of a function applied to the given arguments
+fn apply(function: Function, arguments: Vec<Vec<Node>>) -> Vec<Node> {
+    let mut nodes = vec![Node::Function(function)];
+    nodes.extend(arguments.into_iter().flatten());
+    nodes
+}
+
+/// expression := term (('+' | '-') term)*
+fn parse_sum(chars: &mut Peekable<Chars>) -> Option<Vec<Node>> {
+    let mut nodes = parse_product(chars)?;
+
+    loop {
+        skip_whitespace(chars);
+        let function = match chars.peek() {
+            Some('+') => Function::Add,
+            Some('-') => Function::Sub,
+            _ => return Some(nodes),
+        };
+        chars.next();
+        nodes = apply(function, vec![nodes, parse_product(chars)?]);
+    }
+}
+
+/// term := factor (('*' | '/') factor)*
+fn parse_product(chars: &mut Peekable<Chars>) -> Option<Vec<Node>> {
+    let mut nodes = parse_factor(chars)?;
+
+    loop {
+        skip_whitespace(chars);
+        let function = match chars.peek() {
+            Some('*') => Function::Mul,
+            Some('/') => Function::Div,
+            _ => return Some(nodes),
+        };
+        chars.next();
+        nodes = apply(function, vec![nodes, parse_factor(chars)?]);
+    }
+}
+
+/// factor := '-' factor | '(' expression ')' | number | variable | function '(' expression ')'
+fn parse_factor(chars: &mut Peekable<Chars>) -> Option<Vec<Node>> {
+    skip_whitespace(chars);
+
+    match *chars.peek()? {
+        '-' => {
+            chars.next();
+            Some(apply(
+                Function::Sub,
+                vec![vec![Node::Constant(0.0)], parse_factor(chars)?],
+            ))
+        }
+        '(' => {
+            chars.next();
+            let nodes = parse_sum(chars)?;
+            skip_whitespace(chars);
+            (chars.next()? == ')').then_some(nodes)
+        }
+        c if c.is_ascii_digit() || c == '.' => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            Some(vec![Node::Constant(number.parse().ok()?)])
+        }
+        c if c.is_ascii_alphabetic() => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                name.push(c);
+                chars.next();
+            }
+
+            if name == "x" {
+                return Some(vec![Node::Variable(0)]);
+            }
+            if let Some(Ok(index)) = name.strip_prefix('x').map(str::parse) {
+                return Some(vec![Node::Variable(index)]);
+            }
+
+            let function = match name.as_str() {
+                "sin" => Function::Sin,
+                "cos" => Function::Cos,
+                "exp" => Function::Exp,
+                "log" => Function::Log,
+                _ => return None,
+            };
+            skip_whitespace(chars);
+            if chars.next()? != '(' {
+                return None;
+            }
+            let argument = parse_sum(chars)?;
+            skip_whitespace(chars);
+            (chars.next()? == ')').then_some(apply(function, vec![argument]))
+        }
+        _ => None,
+    }
+}
+
+/// Parse an arithmetic expression over the variables x or x0, x1, ... into a tree
+pub fn parse_expression(text: &str) -> Option<ExpressionTree> {
+    let mut chars = text.chars().peekable();
+    let nodes = parse_sum(&mut chars)?;
+
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return None;
+    }
+
+    Some(ExpressionTree::from_nodes(nodes))
+}
+
+/// Context fitting the expressions produced by the grammar to the samples, minimizing the
+/// mean squared error. Programs that are not valid expressions of the single variable x, or x0,
+/// get an infinite error.
+pub fn grammatical_regression_context(
+    grammar: Grammar,
+    samples: Vec<(f64, f64)>,
+) -> GrammarContext {
+    GrammarContext::new(grammar, move |program| {
+        let tree = match parse_expression(program) {
+            Some(tree) => tree,
+            None => return f64::INFINITY,
+        };
+
+        if tree
+            .nodes()
+            .iter()
+            .any(|n| matches!(n, Node::Variable(i) if *i > 0))
+        {
+            f64::INFINITY
+        } else {
+            mean_squared_error(&tree, &samples)
+        }
+    })
+}
+
+#[test]
+fn test_parse_expression() {
+    let tree = parse_expression("x*(1.0 - x) / -2 + sin(x1)").unwrap();
+
+    assert_eq!(
+        tree.to_string(),
+        "(((x0 * (1.000 - x0)) / (0.000 - 2.000)) + sin(x1))"
+    );
+    assert_eq!(tree.evaluate(&[3.0, 0.0]), 3.0);
+    assert!(parse_expression("x +").is_none());
+    assert!(parse_expression("(x").is_none());
+    assert!(parse_expression("tan(x)").is_none());
+    assert!(parse_expression("xy").is_none());
+}
+
+#[test]
+fn test_grammatical_regression() {
+    use super::regression::quartic_samples;
+    use crate::ea::{GeneticAlgorithm, ParentSelection, Population};
+    use crate::genotypes::Codons;
+
+    let grammar = Grammar::parse(DEFAULT_GRAMMAR).unwrap();
+    let context = grammatical_regression_context(grammar, quartic_samples());
+    let result = GeneticAlgorithm::<Codons>::builder()
+        .seed(4)
+        .population(200)
+        .minimize(true)
+        .target_fitness(1e-9)
+        .crossover_rate(0.9)
+        .parent_selection(ParentSelection::TournamentSelection)
+        .max_generations(60)
+        .build(context)
+        .evolve();
+
+    assert!(result.best.fitness < 0.05, "{}", result.best.fitness);
+}
+
+#[test]
+fn test_grammar_with_unknown_variable() {
+    use super::regression::quartic_samples;
+    use crate::ea::{GeneticAlgorithm, Population};
+    use crate::genotypes::Codons;
+
+    // The samples only have x, so programs using x1 are invalid rather than out of bounds
+    let grammar = Grammar::parse("<expr> ::= <expr>+<v> | <v>\n<v> ::= x1 | x").unwrap();
+    let context = grammatical_regression_context(grammar, quartic_samples());
+    let result = GeneticAlgorithm::<Codons>::builder()
+        .seed(5)
+        .population(20)
+        .minimize(true)
+        .target_fitness(0.0)
+        .max_generations(5)
+        .build(context)
+        .evolve();
+
+    assert!(result.best.fitness.is_finite());
+}
@@ -1,12 +1,28 @@
+mod grammatical;
 mod nqueens;
+mod onemax;
+mod regression;
 mod tsp;
+pub mod tsplib;
 
-use crate::ea::population::Population;
-use crate::ea::population::StandardPopulation;
+use crate::ea::population::{create_rng, StandardPopulation};
+use crate::ea::{Config, ConfigError, ConsoleReporter, GeneticAlgorithmBuilder};
+use crate::genotypes::{BitString, Codons, ExpressionTree, Grammar, GrammarError};
 use crate::Options;
-use nqueens::NQueens;
+use std::io;
 use structopt::clap::arg_enum;
-use tsp::TravelingSalesman;
+use tsplib::TspLibError;
+
+pub use grammatical::{grammatical_regression_context, parse_expression, DEFAULT_GRAMMAR};
+pub use nqueens::{Board, NQueens};
+pub use onemax::{one_max, one_max_context};
+pub use regression::{
+    load_samples, mean_squared_error, quartic_samples, symbolic_regression_context,
+};
+pub use tsp::{TravelingSalesman, TspContext};
+
+/// Moved to the permutation genotype, re-exported here for existing users
+pub use crate::genotypes::PermutationCrossover;
 
 // These are wrapped in arg_enum since we are constructing these directly from StructOpt
 arg_enum! {
@@ -15,25 +31,157 @@ arg_enum! {
     pub enum Problem {
         NQueens,
         TravelingSalesman,
+        OneMax,
+        SymbolicRegression,
+        GrammaticalEvolution,
+        BiObjectiveTsp,
     }
 }
 
 pub fn create_nqueens(options: Options) -> StandardPopulation<NQueens> {
-    StandardPopulation::<NQueens>::new(options)
+    GeneticAlgorithmBuilder::new(Config::from(&options))
+        .observer(ConsoleReporter::new(options.debug))
+        .build(options.problem_size)
+}
+
+/// Create a OneMax population evolving bit strings of the problem size
+pub fn create_onemax(options: Options) -> Result<StandardPopulation<BitString>, ConfigError> {
+    let mut context = one_max_context(options.problem_size, options.bit_crossover);
+    if let Some(gene_rate) = options.gene_rate {
+        if !(0.0..=1.0).contains(&gene_rate) {
+            return Err(ConfigError(format!(
+                "gene rate {} is not between 0 and 1",
+                gene_rate
+            )));
+        }
+        context = context.gene_rate(gene_rate);
+    }
+
+    Ok(GeneticAlgorithmBuilder::new(Config::from(&options))
+        .observer(ConsoleReporter::new(options.debug))
+        .build(context))
+}
+
+/// Load the distances between the cities of the TSPLIB instance in the options, or generate
+/// random cities. Also returns the length of the optimal tour, if known.
+fn load_cities(
+    options: &mut Options,
+    rng: &mut impl rand::Rng,
+) -> Result<(Vec<f64>, Option<f64>), TspLibError> {
+    let path = match &options.instance {
+        Some(path) => path.clone(),
+        None => return Ok((tsp::create_random_cities(options.problem_size, rng), None)),
+    };
+
+    let instance = tsplib::load_instance(&path)?;
+    options.problem_size = instance.dimension;
+
+    // Look for a known optimal tour next to the instance unless one was given
+    let tour_path = match &options.optimal_tour {
+        Some(tour_path) => Some(tour_path.clone()),
+        None => Some(path.with_extension("opt.tour")).filter(|p| p.exists()),
+    };
+    let optimum = match tour_path {
+        Some(tour_path) => {
+            let tour = tsplib::load_tour(&tour_path, instance.dimension)?;
+            Some(tsplib::tour_length(&tour, &instance.distances))
+        }
+        None => None,
+    };
+
+    Ok((instance.distances, optimum))
 }
 
+/// Create a traveling salesman population, along with the length of the optimal tour if known
 pub fn create_tsp(
     mut options: Options,
-) -> (StandardPopulation<TravelingSalesman<'static>>, Vec<f64>) {
-    // Don't have crossover yet
-    options.crossover_rate = 0.0;
+) -> Result<(StandardPopulation<TravelingSalesman>, Option<f64>), TspLibError> {
     // Have to minimize fitness
     options.minimize = true;
 
-    let distances = tsp::create_random_cities(options.problem_size);
+    let mut rng = create_rng(options.seed);
+    let (distances, optimum) = load_cities(&mut options, &mut rng)?;
+    let context = TspContext {
+        cities: options.problem_size,
+        distances,
+        crossover: options.permutation_crossover,
+        mutation: options.permutation_mutation,
+        costs: None,
+    };
+
+    let mut population =
+        StandardPopulation::<TravelingSalesman>::with_rng(Config::from(&options), context, rng);
+    population.add_observer(Box::new(ConsoleReporter::new(options.debug)));
+
+    Ok((population, optimum))
+}
+
+/// Create a population minimizing both the length of a tour and its total cost over a second
+/// matrix of random costs between the cities, to be evolved by a multi-objective engine
+pub fn create_bi_objective_tsp(
+    mut options: Options,
+) -> Result<StandardPopulation<TravelingSalesman>, TspLibError> {
+    // Both objectives have to be minimized
+    options.minimize = true;
 
-    (
-        StandardPopulation::<TravelingSalesman>::new(options),
+    let mut rng = create_rng(options.seed);
+    let (distances, _) = load_cities(&mut options, &mut rng)?;
+    let costs = tsp::create_random_cities(options.problem_size, &mut rng);
+    let context = TspContext {
+        cities: options.problem_size,
         distances,
-    )
+        crossover: options.permutation_crossover,
+        mutation: options.permutation_mutation,
+        costs: Some(costs),
+    };
+
+    let mut population =
+        StandardPopulation::<TravelingSalesman>::with_rng(Config::from(&options), context, rng);
+    population.add_observer(Box::new(ConsoleReporter::new(options.debug)));
+
+    Ok(population)
+}
+
+/// Create a symbolic regression population fitting the samples in the options, or Koza's
+/// quartic polynomial
+pub fn create_symbolic_regression(
+    mut options: Options,
+) -> io::Result<StandardPopulation<ExpressionTree>> {
+    // The mean squared error has to be minimized
+    options.minimize = true;
+
+    let samples = match &options.samples {
+        Some(path) => load_samples(path)?,
+        None => quartic_samples(),
+    };
+    let context = symbolic_regression_context(samples, options.functions.clone())
+        .max_depth(options.max_depth)
+        .mutation(options.tree_mutation);
+
+    Ok(GeneticAlgorithmBuilder::new(Config::from(&options))
+        .observer(ConsoleReporter::new(options.debug))
+        .build(context))
+}
+
+/// Create a grammatical evolution population fitting the expressions of the grammar in the
+/// options, or a default arithmetic grammar, to the samples in the options
+pub fn create_grammatical_evolution(
+    mut options: Options,
+) -> Result<StandardPopulation<Codons>, GrammarError> {
+    // The mean squared error has to be minimized
+    options.minimize = true;
+
+    let grammar = match &options.grammar {
+        Some(path) => Grammar::load(path)?,
+        None => Grammar::parse(DEFAULT_GRAMMAR)?,
+    };
+    let samples = match &options.samples {
+        Some(path) => load_samples(path)?,
+        None => quartic_samples(),
+    };
+    let context = grammatical_regression_context(grammar, samples).max_wraps(options.max_wraps);
+
+    Ok(GeneticAlgorithmBuilder::new(Config::from(&options))
+        .observer(ConsoleReporter::new(options.debug))
+        .build(context))
 }
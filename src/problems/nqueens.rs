@@ -1,13 +1,13 @@
 /// Implementation of the N-queens problem
 use crate::ea::{Genotype, Phenotype};
-use crate::Options;
 use rand::distributions::{Distribution, Uniform};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 // Simple N! implementation
 fn factorial(n: u64) -> u64 {
-    (1..=n).fold(1, |acc, v| acc * v)
+    (1..=n).product()
 }
 
 // Max non-attacking queens in N-queens problem is N choose K=2 = !N / K!(N-K)!
@@ -15,20 +15,18 @@ fn max_clashes(n: usize) -> u32 {
     (factorial(n as u64) / (2 * factorial(n as u64 - 2))) as u32
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct NQueens {
+    /// Row of the queen in every column
     genome: Vec<usize>,
-    max_clashes: u32,
     problem_size: usize,
 }
 
 impl NQueens {
     fn to_grid(&self) -> Vec<Vec<usize>> {
-        let mut grid = Vec::with_capacity(self.problem_size);
-        grid.resize(self.problem_size, Vec::new());
+        let mut grid = vec![vec![0; self.problem_size]; self.problem_size];
 
         for x in 0..self.genome.len() {
-            grid[self.genome[x]].resize(self.problem_size, 0);
             grid[self.genome[x]][x] = 1;
         }
 
@@ -38,51 +36,73 @@ impl NQueens {
 
 impl fmt::Display for NQueens {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut rows = String::new();
-        let grid = self.to_grid();
+        write!(f, "{:?}", self.genome)
+    }
+}
 
-        for row in 0..grid.len() - 1 {
-            rows.push_str(format!("{:?}\n", grid[row]).as_str());
-        }
-        rows.push_str(format!("{:?}", grid[grid.len() - 1]).as_str());
+/// A chess board with a queen on the squares set to 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    pub squares: Vec<Vec<usize>>,
+    /// Row of the queen in every column
+    pub queens: Vec<usize>,
+}
 
-        write!(f, "{}", rows)
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows: Vec<String> = self
+            .squares
+            .iter()
+            .map(|row| format!("{:?}", row))
+            .collect();
+
+        write!(f, "{}", rows.join("\n"))
     }
 }
 
 impl Genotype for NQueens {
+    /// Size of the board
+    type Context = usize;
+    type Phenotype = Board;
+
     /// Create a new NQueens specimen
-    fn new(rng: &mut impl Rng, options: &Options) -> Self {
-        let mut genome: Vec<usize> = Vec::with_capacity(options.problem_size);
-        let range = Uniform::from(0..options.problem_size);
+    fn new(rng: &mut impl Rng, problem_size: &usize) -> Self {
+        let problem_size = *problem_size;
+        let mut genome: Vec<usize> = Vec::with_capacity(problem_size);
+        let range = Uniform::from(0..problem_size);
 
-        for _ in 0..options.problem_size {
+        for _ in 0..problem_size {
             genome.push(range.sample(rng));
         }
 
         NQueens {
             genome,
-            max_clashes: max_clashes(options.problem_size),
-            problem_size: options.problem_size,
+            problem_size,
+        }
+    }
+
+    /// Place the queens on a board
+    fn decode(&self, _context: &usize) -> Board {
+        Board {
+            squares: self.to_grid(),
+            queens: self.genome.clone(),
         }
     }
 
     /// Mutate this genome in random locations
-    fn mutate(&mut self, rng: &mut impl Rng) {
+    fn mutate(&mut self, _context: &usize, rng: &mut impl Rng) {
         let a = rng.gen_range(0..self.problem_size);
         let b = rng.gen_range(0..self.problem_size);
 
         if rng.gen_bool(0.5) {
             self.genome[a] = b;
         } else {
-            let t = self.genome[a];
-            self.genome[a] = self.genome[b];
-            self.genome[b] = t;
+            self.genome.swap(a, b);
         }
     }
 
     /// Create a new specimen by performing crossover with other at random index
-    fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Self {
+    fn crossover(&self, other: &Self, _context: &usize, rng: &mut impl Rng) -> Self {
         let mut genome = Vec::with_capacity(self.problem_size);
         let index = rng.gen_range(0..self.problem_size);
 
@@ -95,22 +115,19 @@ impl Genotype for NQueens {
 
         Self {
             genome,
-            max_clashes: max_clashes(self.problem_size),
             problem_size: self.problem_size,
         }
     }
 }
 
-impl Phenotype for NQueens {
-    fn fitness(&self) -> f64 {
+impl Phenotype<usize> for Board {
+    fn fitness(&self, problem_size: &usize) -> f64 {
+        let queens = &self.queens;
+        let max_clashes = max_clashes(*problem_size);
         let mut clashes: u32 = 0;
 
-        for x in 0..self.genome.len() {
-            let y: usize = self.genome[x];
-
-            for i in 0..x {
-                let other = self.genome[i];
-
+        for (x, &y) in queens.iter().enumerate() {
+            for (i, &other) in queens[..x].iter().enumerate() {
                 if other == y || (other as i32 - y as i32).abs() == (x as i32 - i as i32).abs() {
                     clashes += 1;
                 }
@@ -119,8 +136,33 @@ impl Phenotype for NQueens {
 
         // Max number of non-attacking queen pairs is N choose 2 for an NxN board
         // For N=8 this is 28
-        let fitness = self.max_clashes as f64 / (self.max_clashes + clashes) as f64;
-
-        fitness
+        max_clashes as f64 / (max_clashes + clashes) as f64
     }
 }
+
+#[test]
+fn test_board_is_decoded() {
+    use crate::ea::{GeneticAlgorithm, Population};
+
+    let queens = NQueens {
+        genome: vec![1, 3, 0, 2],
+        problem_size: 4,
+    };
+    let board = queens.decode(&4);
+
+    assert_eq!(
+        board.to_string(),
+        "[0, 0, 1, 0]\n[1, 0, 0, 0]\n[0, 0, 0, 1]\n[0, 1, 0, 0]"
+    );
+    assert_eq!(board.fitness(&4), 1.0);
+
+    let result = GeneticAlgorithm::<NQueens>::builder()
+        .seed(1)
+        .max_generations(50)
+        .build(8)
+        .evolve();
+    let phenotype = result.best.phenotype.as_ref().unwrap();
+
+    assert_eq!(*phenotype, result.best.genotype.decode(&8));
+    assert_eq!(phenotype.fitness(&8), result.best.fitness);
+}
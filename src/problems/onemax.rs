@@ -0,0 +1,37 @@
+/// Implementation of the OneMax problem, maximizing the number of set bits
+use crate::genotypes::{BitCrossover, BitString, BitStringContext};
+
+/// Fraction of set bits, 1.0 when all bits are set, including the empty string
+pub fn one_max(bits: &BitString) -> f64 {
+    if bits.is_empty() {
+        return 1.0;
+    }
+
+    bits.count_ones() as f64 / bits.len() as f64
+}
+
+/// Context for OneMax with the given number of bits
+pub fn one_max_context(length: usize, crossover: BitCrossover) -> BitStringContext {
+    BitStringContext::new(length, one_max).crossover(crossover)
+}
+
+#[test]
+fn test_one_max_is_solved() {
+    use crate::ea::{GeneticAlgorithm, ParentSelection, Population, TerminationReason};
+
+    let result = GeneticAlgorithm::<BitString>::builder()
+        .seed(1)
+        .parent_selection(ParentSelection::TournamentSelection)
+        .mutation_rate(0.5)
+        .max_generations(500)
+        .build(one_max_context(100, BitCrossover::Uniform))
+        .evolve();
+
+    assert_eq!(result.termination, TerminationReason::TargetFitness);
+    assert_eq!(result.best.genotype.count_ones(), 100);
+}
+
+#[test]
+fn test_one_max_of_empty_string() {
+    assert_eq!(one_max(&BitString::zeros(0)), 1.0);
+}
@@ -0,0 +1,118 @@
+/// Symbolic regression, evolving an expression that fits a set of (x, y) samples
+use crate::genotypes::{ExpressionTree, Function, TreeContext};
+use std::path::Path;
+use std::{fs, io};
+
+/// Read (x, y) samples from a CSV file with one sample per line. Empty lines and a first line
+/// without any numbers, taken as a header, are skipped.
+pub fn load_samples(path: &Path) -> io::Result<Vec<(f64, f64)>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut samples = Vec::new();
+
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+
+        if line.trim().is_empty() {
+            continue;
+        }
+        if fields.len() != 2 {
+            return Err(invalid(format!(
+                "line {}: expected x,y but found {:?}",
+                number + 1,
+                line
+            )));
+        }
+
+        match (fields[0].parse(), fields[1].parse()) {
+            (Ok(x), Ok(y)) => samples.push((x, y)),
+            // A header names both columns, a single bad number is a broken sample
+            (Err(_), Err(_)) if number == 0 => continue,
+            _ => {
+                return Err(invalid(format!(
+                    "line {}: invalid number in {:?}",
+                    number + 1,
+                    line
+                )))
+            }
+        }
+    }
+
+    if samples.is_empty() {
+        return Err(invalid(format!("no samples in {}", path.display())));
+    }
+
+    Ok(samples)
+}
+
+/// Samples of Koza's quartic polynomial x⁴ + x³ + x² + x at 20 points in [-1, 1]
+pub fn quartic_samples() -> Vec<(f64, f64)> {
+    (0..20)
+        .map(|i| {
+            let x = -1.0 + 2.0 * i as f64 / 19.0;
+            (x, x.powi(4) + x.powi(3) + x.powi(2) + x)
+        })
+        .collect()
+}
+
+/// Mean squared error of the tree over the samples, infinite if it is not a number
+pub fn mean_squared_error(tree: &ExpressionTree, samples: &[(f64, f64)]) -> f64 {
+    let error = samples
+        .iter()
+        .map(|&(x, y)| (tree.evaluate(&[x]) - y).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64;
+
+    if error.is_finite() {
+        error
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Context for fitting expressions of a single variable over the given functions to the
+/// samples, which should be minimized
+pub fn symbolic_regression_context(
+    samples: Vec<(f64, f64)>,
+    functions: Vec<Function>,
+) -> TreeContext {
+    TreeContext::new(functions, 1, move |tree| mean_squared_error(tree, &samples))
+}
+
+#[test]
+fn test_quartic_is_fitted() {
+    use crate::ea::{GeneticAlgorithm, ParentSelection, Population};
+
+    let context = symbolic_regression_context(
+        quartic_samples(),
+        vec![Function::Add, Function::Sub, Function::Mul, Function::Div],
+    )
+    .constants(None);
+    let result = GeneticAlgorithm::<ExpressionTree>::builder()
+        .seed(7)
+        .population(200)
+        .minimize(true)
+        .target_fitness(1e-9)
+        .crossover_rate(0.9)
+        .parent_selection(ParentSelection::TournamentSelection)
+        .tournament_size(5)
+        .max_generations(100)
+        .build(context)
+        .evolve();
+
+    assert!(result.best.fitness < 0.01, "{}", result.best.genotype);
+    assert!(result.best.genotype.depth() <= 17);
+}
+
+#[test]
+fn test_samples_header_is_skipped() {
+    let path = std::env::temp_dir().join(format!("rust-ga-samples-{}.csv", std::process::id()));
+
+    std::fs::write(&path, "x,y\n1,2\n\n3,4\n").unwrap();
+    assert_eq!(load_samples(&path).unwrap(), vec![(1.0, 2.0), (3.0, 4.0)]);
+
+    // A first sample with one bad number is not a header
+    std::fs::write(&path, "1,y\n3,4\n").unwrap();
+    let error = load_samples(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(error.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
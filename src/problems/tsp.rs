@@ -1,12 +1,15 @@
 /// Implementation of the traveling salesman problem
-use crate::ea::{Genotype, Phenotype};
-use crate::Options;
+use super::tsplib::tour_length;
+use crate::ea::{Genotype, MultiObjective, Phenotype};
+use crate::genotypes::{
+    crossover_permutations, mutate_permutation, random_permutation, shift_elements,
+    PermutationCrossover, PermutationMutation,
+};
 use rand::Rng;
-use rand::{seq::SliceRandom, thread_rng};
+use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, fmt};
 
-pub fn create_random_cities(n: usize) -> Vec<f64> {
-    let mut rng = thread_rng();
+pub fn create_random_cities(n: usize, rng: &mut impl Rng) -> Vec<f64> {
     let mut cities: Vec<(i32, i32)> = Vec::with_capacity(n);
     let mut distance_matrix: Vec<f64> = Vec::with_capacity(n * n);
 
@@ -28,79 +31,137 @@ pub fn create_random_cities(n: usize) -> Vec<f64> {
     distance_matrix
 }
 
-/// Shift the elements "from" index "to" index by an offset of "shift".
-/// "shift" will wrap by the length of the array
-fn shift_elements(genome: &mut Vec<usize>, from: usize, to: usize, shift: usize) {
-    if from >= to {
-        panic!("from >= to, from={}, to={}", from, to);
-    }
+/// Distance between two cities
+fn distance(distances: &[f64], length: usize, a: usize, b: usize) -> f64 {
+    distances[a * length + b]
+}
+
+/// Apply the first improving 2-opt move, reversing the path between two edges.
+/// Returns whether the path was improved.
+fn two_opt(path: &mut [usize], distances: &[f64]) -> bool {
+    let length = path.len();
+
+    for i in 0..length - 2 {
+        // The first and last edge are adjacent when starting at the first city
+        let last = if i == 0 { length - 1 } else { length };
 
-    for i in (from..to).rev() {
-        let pos = (i + shift) % genome.len();
-        let shoved = genome[i];
+        for j in i + 2..last {
+            let (a, b) = (path[i], path[i + 1]);
+            let (c, d) = (path[j], path[(j + 1) % length]);
+            let delta = distance(distances, length, a, c) + distance(distances, length, b, d)
+                - distance(distances, length, a, b)
+                - distance(distances, length, c, d);
 
-        genome[i] = genome[pos];
-        genome[pos] = shoved;
+            if delta < -1e-9 {
+                path[i + 1..=j].reverse();
+                return true;
+            }
+        }
     }
+
+    false
 }
 
-fn create_random_path(length: usize, rng: &mut impl Rng) -> Vec<usize> {
-    let mut genome: Vec<usize> = Vec::with_capacity(length);
+/// Apply the first improving Or-opt move, relocating a segment of up to three cities to
+/// another position in the path. Returns whether the path was improved.
+fn or_opt(path: &mut Vec<usize>, distances: &[f64]) -> bool {
+    let length = path.len();
+    let d = |a: usize, b: usize| distance(distances, length, a, b);
+
+    for segment in 1..=3 {
+        for i in 0..=length - segment {
+            let first = path[i];
+            let last = path[i + segment - 1];
+            let prev = path[(i + length - 1) % length];
+            let next = path[(i + segment) % length];
+            let removed = d(prev, first) + d(last, next) - d(prev, next);
+
+            for j in 0..length {
+                // Skip edges touching the segment
+                if j + 1 >= i && j < i + segment {
+                    continue;
+                }
+                if i == 0 && j == length - 1 {
+                    continue;
+                }
+
+                let (p, q) = (path[j], path[(j + 1) % length]);
+
+                if d(p, first) + d(last, q) - d(p, q) - removed < -1e-9 {
+                    let moved: Vec<usize> = path.drain(i..i + segment).collect();
+                    let position = path.iter().position(|&c| c == p).unwrap() + 1;
+                    path.splice(position..position, moved);
+                    return true;
+                }
+            }
+        }
+    }
 
-    genome.extend(0..length);
-    genome.shuffle(rng);
+    false
+}
 
-    genome
+/// Problem instance shared by all paths in a population
+#[derive(Debug)]
+pub struct TspContext {
+    /// Number of cities
+    pub cities: usize,
+    /// Row major matrix of distances between each pair of cities
+    pub distances: Vec<f64>,
+    /// Crossover operator used to recombine paths
+    pub crossover: PermutationCrossover,
+    /// Mutation operator, or None for a mix of swaps, shifts and new random paths
+    pub mutation: Option<PermutationMutation>,
+    /// Row major matrix of a second cost between each pair of cities, making the problem
+    /// bi-objective
+    pub costs: Option<Vec<f64>>,
 }
 
-#[derive(Debug, PartialEq)]
-pub struct TravelingSalesman<'a> {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TravelingSalesman {
     genome: Vec<usize>,
-    pub distances: Option<&'a Vec<f64>>,
 }
 
-impl<'a> PartialOrd for TravelingSalesman<'a> {
+impl PartialOrd for TravelingSalesman {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.genome.cmp(&other.genome))
     }
 }
 
-impl<'a> fmt::Display for TravelingSalesman<'a> {
+impl fmt::Display for TravelingSalesman {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self.genome)
     }
 }
 
-impl<'a> Genotype for TravelingSalesman<'a> {
+impl Genotype for TravelingSalesman {
+    type Context = TspContext;
+    type Phenotype = Self;
+
     /// Create a new TSP specimen
-    fn new(rng: &mut impl Rng, options: &Options) -> Self {
+    fn new(rng: &mut impl Rng, context: &TspContext) -> Self {
         Self {
-            genome: create_random_path(options.problem_size, rng),
-            distances: None,
+            genome: random_permutation(context.cities, rng),
         }
     }
 
+    fn decode(&self, _context: &TspContext) -> Self {
+        self.clone()
+    }
+
     /// Mutate this genome in random locations
-    fn mutate(&mut self, rng: &mut impl Rng) {
+    fn mutate(&mut self, context: &TspContext, rng: &mut impl Rng) {
         let length = self.genome.len();
 
+        if let Some(mutation) = context.mutation {
+            mutate_permutation(mutation, &mut self.genome, rng);
         // 50% chance to randomize cities
-        if rng.gen_bool(0.5) {
+        } else if rng.gen_bool(0.5) {
             // 80% chance to swap two random cities
             if rng.gen_bool(0.8) {
-                let a = rng.gen_range(0..length);
-                let mut b = rng.gen_range(0..length);
-
-                while a == b {
-                    b = rng.gen_range(0..length);
-                }
-
-                let tmp = self.genome[a];
-                self.genome[a] = self.genome[b];
-                self.genome[b] = tmp;
+                mutate_permutation(PermutationMutation::Swap, &mut self.genome, rng);
             // 20% change to get a new random path
             } else {
-                self.genome = create_random_path(length, rng);
+                self.genome = random_permutation(length, rng);
             }
         // 50% chance to shift a subgroup around
         } else {
@@ -112,27 +173,33 @@ impl<'a> Genotype for TravelingSalesman<'a> {
         }
     }
 
-    /// Create a new specimen by performing crossover with other at random index
-    fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Self {
-        let mut genome = other.genome.clone();
-        let index = rng.gen_range(0..self.genome.len());
-
-        for i in 0..index {
-            genome[i] = self.genome[i];
+    /// Create a new specimen using the configured order preserving crossover
+    fn crossover(&self, other: &Self, context: &TspContext, rng: &mut impl Rng) -> Self {
+        Self {
+            genome: crossover_permutations(context.crossover, &self.genome, &other.genome, rng),
         }
+    }
 
-        Self {
-            genome,
-            distances: self.distances,
+    /// Run 2-opt and Or-opt moves until the path is locally optimal
+    fn improve(&self, context: &TspContext) -> Option<Self> {
+        let distances = &context.distances;
+        let mut genome = self.genome.clone();
+
+        if genome.len() < 5 {
+            return None;
         }
+
+        while two_opt(&mut genome, distances) || or_opt(&mut genome, distances) {}
+
+        Some(Self { genome })
     }
 }
 
-impl<'a> Phenotype for TravelingSalesman<'a> {
-    fn fitness(&self) -> f64 {
+impl Phenotype<TspContext> for TravelingSalesman {
+    fn fitness(&self, context: &TspContext) -> f64 {
         let length = self.genome.len();
         let mut distance: f64 = 0.0;
-        let distances = self.distances.unwrap();
+        let distances = &context.distances;
 
         for i in 0..length {
             let x = self.genome[i];
@@ -141,26 +208,113 @@ impl<'a> Phenotype for TravelingSalesman<'a> {
             distance += distances[y * length + x];
         }
 
-        distance as f64
+        distance
+    }
+}
+
+impl MultiObjective<TspContext> for TravelingSalesman {
+    /// Length of the tour, followed by its total cost if the context has costs
+    fn objectives(&self, context: &TspContext) -> Vec<f64> {
+        let mut objectives = vec![self.fitness(context)];
+
+        if let Some(costs) = &context.costs {
+            objectives.push(tour_length(&self.genome, costs));
+        }
+
+        objectives
     }
 }
 
 #[test]
-fn test_mutate() {
-    // let tsp = TravelingSalesman::new(&mut thread_rng());
-    let mut cities = vec![1, 2, 3, 4, 5];
-    shift_elements(&mut cities, 0, 2, 1);
-    assert_eq!(cities, [3, 1, 2, 4, 5]);
-
-    cities = vec![1, 2, 3, 4, 5];
-    shift_elements(&mut cities, 3, 5, 2);
-    assert_eq!(cities, [4, 5, 3, 1, 2]);
-
-    cities = vec![1, 2, 3, 4, 5];
-    shift_elements(&mut cities, 0, 3, 1);
-    assert_eq!(cities, [4, 1, 2, 3, 5]);
-
-    cities = vec![1, 2, 3, 4, 5];
-    shift_elements(&mut cities, 0, 5, 5);
-    assert_eq!(cities, [1, 2, 3, 4, 5]);
+fn test_local_search() {
+    use crate::ea::{GeneticAlgorithm, LocalSearch, Population};
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+    let context = TspContext {
+        cities: 30,
+        distances: create_random_cities(30, &mut rng),
+        crossover: PermutationCrossover::Order,
+        mutation: None,
+        costs: None,
+    };
+    let tsp = TravelingSalesman {
+        genome: random_permutation(30, &mut rng),
+    };
+    let mut improved = tsp.improve(&context).unwrap();
+
+    assert!(improved.fitness(&context) < tsp.fitness(&context));
+    // A locally optimal path can not be improved by any further moves
+    assert!(!two_opt(&mut improved.genome, &context.distances));
+    assert!(!or_opt(&mut improved.genome, &context.distances));
+
+    improved.genome.sort_unstable();
+    assert_eq!(improved.genome, (0..30).collect::<Vec<usize>>());
+
+    // Baldwinian search keeps the fitness of the improved tour but the unchanged phenotype
+    let distances = context.distances.clone();
+    let mut ga = GeneticAlgorithm::<TravelingSalesman>::builder()
+        .seed(3)
+        .population(10)
+        .minimize(true)
+        .target_fitness(0.0)
+        .max_generations(2)
+        .local_search(LocalSearch::Baldwinian)
+        .build(context);
+    ga.evolve();
+    for individual in ga.individuals() {
+        let length = tour_length(&individual.genotype.genome, &distances);
+        assert_eq!(individual.phenotype.as_ref(), Some(&individual.genotype));
+        assert!(individual.fitness < length);
+    }
+}
+
+#[test]
+fn test_bi_objective_front() {
+    use crate::ea::{dominates, GeneticAlgorithm, GeneticAlgorithmBuilder, Population};
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2);
+    let distances = create_random_cities(12, &mut rng);
+    let costs = create_random_cities(12, &mut rng);
+    let context = || TspContext {
+        cities: 12,
+        distances: distances.clone(),
+        crossover: PermutationCrossover::Order,
+        mutation: Some(PermutationMutation::Inversion),
+        costs: Some(costs.clone()),
+    };
+    let builder = || -> GeneticAlgorithmBuilder<TravelingSalesman> {
+        GeneticAlgorithm::builder()
+            .seed(5)
+            .population(40)
+            .minimize(true)
+            .target_fitness(0.0)
+            .max_generations(60)
+            .reference_point(vec![4000.0, 3500.0])
+    };
+    let nsga2 = builder().build_nsga2(context()).evolve();
+    let front = &nsga2.pareto_front;
+    assert!(front.iter().any(|i| i.fitness == nsga2.best.fitness));
+
+    let results = vec![
+        nsga2,
+        builder().build_spea2(context()).evolve(),
+        builder().build_moead(context()).evolve(),
+    ];
+
+    for result in results {
+        let front = &result.pareto_front;
+
+        // Measured from just beyond the worst front of the three engines, all of them cover
+        // over a quarter of the 4000 x 3500 box
+        assert!(front.len() > 1);
+        assert!(result.hypervolume.unwrap() > 3.5e6);
+        for a in front {
+            assert_eq!(a.objectives.len(), 2);
+            assert!(front
+                .iter()
+                .all(|b| !dominates(&b.objectives, &a.objectives, true)));
+        }
+    }
 }
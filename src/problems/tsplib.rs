@@ -0,0 +1,385 @@
+/// Parser for TSPLIB instance (.tsp) and tour (.opt.tour) files
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fmt, fs, io};
+
+/// Errors that can occur while loading TSPLIB files
+#[derive(Debug)]
+pub enum TspLibError {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for TspLibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TspLibError::Io(e) => write!(f, "{}", e),
+            TspLibError::Parse(message) => write!(f, "Invalid TSPLIB file: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for TspLibError {
+    fn from(e: io::Error) -> Self {
+        TspLibError::Io(e)
+    }
+}
+
+fn parse_error<T>(message: String) -> Result<T, TspLibError> {
+    Err(TspLibError::Parse(message))
+}
+
+/// A symmetric TSP instance with a precomputed distance matrix
+#[derive(Debug)]
+pub struct TspInstance {
+    pub name: String,
+    pub dimension: usize,
+    /// Row major dimension x dimension matrix of distances between cities
+    pub distances: Vec<f64>,
+}
+
+/// Nearest integer as defined by TSPLIB
+fn nint(x: f64) -> f64 {
+    (x + 0.5).floor()
+}
+
+/// Convert a TSPLIB DDD.MM coordinate to radians
+fn geo_radians(x: f64) -> f64 {
+    // TSPLIB defines GEO distances with this truncated value of PI
+    #[allow(clippy::approx_constant)]
+    let pi = 3.141592;
+    let degrees = x.trunc();
+    let minutes = x - degrees;
+
+    pi * (degrees + 5.0 * minutes / 3.0) / 180.0
+}
+
+/// Distance between two nodes for the given EDGE_WEIGHT_TYPE
+fn distance(edge_weight_type: &str, a: (f64, f64), b: (f64, f64)) -> Result<f64, TspLibError> {
+    let dx = a.0 - b.0;
+    let dy = a.1 - b.1;
+
+    let d = match edge_weight_type {
+        "EUC_2D" => nint((dx * dx + dy * dy).sqrt()),
+        "CEIL_2D" => (dx * dx + dy * dy).sqrt().ceil(),
+        "ATT" => {
+            let r = ((dx * dx + dy * dy) / 10.0).sqrt();
+            let t = nint(r);
+
+            if t < r {
+                t + 1.0
+            } else {
+                t
+            }
+        }
+        "GEO" => {
+            let radius = 6378.388;
+            let (lat_a, lon_a) = (geo_radians(a.0), geo_radians(a.1));
+            let (lat_b, lon_b) = (geo_radians(b.0), geo_radians(b.1));
+            let q1 = (lon_a - lon_b).cos();
+            let q2 = (lat_a - lat_b).cos();
+            let q3 = (lat_a + lat_b).cos();
+
+            (radius * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0).trunc()
+        }
+        _ => return parse_error(format!("Unsupported EDGE_WEIGHT_TYPE {}", edge_weight_type)),
+    };
+
+    Ok(d)
+}
+
+/// Build the distance matrix from explicit edge weights in the given EDGE_WEIGHT_FORMAT. Only
+/// symmetric instances are supported, so a FULL_MATRIX must equal its transpose.
+fn explicit_distances(format: &str, n: usize, weights: &[f64]) -> Result<Vec<f64>, TspLibError> {
+    let mut matrix = vec![0.0; n * n];
+    let mut pairs: Vec<(usize, usize)> = Vec::with_capacity(n * n);
+
+    match format {
+        "FULL_MATRIX" => {
+            for y in 0..n {
+                pairs.extend((0..n).map(|x| (y, x)));
+            }
+        }
+        "UPPER_ROW" => {
+            for y in 0..n {
+                pairs.extend((y + 1..n).map(|x| (y, x)));
+            }
+        }
+        "LOWER_DIAG_ROW" => {
+            for y in 0..n {
+                pairs.extend((0..=y).map(|x| (y, x)));
+            }
+        }
+        _ => return parse_error(format!("Unsupported EDGE_WEIGHT_FORMAT {}", format)),
+    }
+
+    if weights.len() != pairs.len() {
+        return parse_error(format!(
+            "Expected {} edge weights for {}, found {}",
+            pairs.len(),
+            format,
+            weights.len()
+        ));
+    }
+
+    if format == "FULL_MATRIX" {
+        if let Some((y, x)) = pairs
+            .iter()
+            .find(|&&(y, x)| weights[y * n + x] != weights[x * n + y])
+        {
+            return parse_error(format!(
+                "Asymmetric FULL_MATRIX, edge weights of {} to {} and back differ",
+                y + 1,
+                x + 1
+            ));
+        }
+    }
+
+    for (&(y, x), &w) in pairs.iter().zip(weights) {
+        matrix[y * n + x] = w;
+        matrix[x * n + y] = w;
+    }
+
+    Ok(matrix)
+}
+
+/// Parse the contents of a TSPLIB .tsp file
+pub fn parse_instance(content: &str) -> Result<TspInstance, TspLibError> {
+    let mut spec: HashMap<String, String> = HashMap::new();
+    let mut coordinates: Vec<(f64, f64)> = Vec::new();
+    let mut weights: Vec<f64> = Vec::new();
+    let mut section = "";
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line {
+            "NODE_COORD_SECTION" | "EDGE_WEIGHT_SECTION" | "DISPLAY_DATA_SECTION" => {
+                section = line;
+                continue;
+            }
+            "EOF" => break,
+            _ => {}
+        }
+
+        if let Some(colon) = line.find(':') {
+            spec.insert(
+                line[..colon].trim().to_uppercase(),
+                line[colon + 1..].trim().to_string(),
+            );
+            section = "";
+            continue;
+        }
+
+        let mut values = Vec::new();
+        for token in line.split_whitespace() {
+            match token.parse::<f64>() {
+                Ok(v) => values.push(v),
+                Err(_) => return parse_error(format!("Unexpected value {}", token)),
+            }
+        }
+
+        match section {
+            "NODE_COORD_SECTION" => {
+                if values.len() < 3 {
+                    return parse_error(format!("Invalid node coordinate {}", line));
+                }
+                coordinates.push((values[1], values[2]));
+            }
+            "EDGE_WEIGHT_SECTION" => weights.extend(values),
+            "DISPLAY_DATA_SECTION" => {}
+            _ => return parse_error(format!("Unexpected line {}", line)),
+        }
+    }
+
+    let dimension: usize = match spec.get("DIMENSION").map(|d| d.parse()) {
+        Some(Ok(d)) => d,
+        _ => return parse_error("Missing or invalid DIMENSION".to_string()),
+    };
+    let edge_weight_type = spec
+        .get("EDGE_WEIGHT_TYPE")
+        .map(String::as_str)
+        .unwrap_or("EUC_2D");
+
+    let distances = if edge_weight_type == "EXPLICIT" {
+        let format = spec
+            .get("EDGE_WEIGHT_FORMAT")
+            .map(String::as_str)
+            .unwrap_or("FULL_MATRIX");
+
+        explicit_distances(format, dimension, &weights)?
+    } else {
+        if coordinates.len() != dimension {
+            return parse_error(format!(
+                "Expected {} node coordinates, found {}",
+                dimension,
+                coordinates.len()
+            ));
+        }
+
+        let mut distances = Vec::with_capacity(dimension * dimension);
+        for a in &coordinates {
+            for b in &coordinates {
+                distances.push(distance(edge_weight_type, *a, *b)?);
+            }
+        }
+        distances
+    };
+
+    Ok(TspInstance {
+        name: spec.remove("NAME").unwrap_or_default(),
+        dimension,
+        distances,
+    })
+}
+
+/// Parse the contents of a TSPLIB .tour file into a zero indexed list of cities
+pub fn parse_tour(content: &str, dimension: usize) -> Result<Vec<usize>, TspLibError> {
+    let mut tour = Vec::new();
+    let mut in_tour = false;
+
+    for token in content.split_whitespace() {
+        if token == "TOUR_SECTION" {
+            in_tour = true;
+        } else if in_tour {
+            match token.parse::<i64>() {
+                Ok(-1) => break,
+                Ok(city) if city > 0 => tour.push(city as usize - 1),
+                _ => return parse_error(format!("Invalid city {} in tour", token)),
+            }
+        }
+    }
+
+    if tour.is_empty() {
+        return parse_error("Missing TOUR_SECTION".to_string());
+    }
+
+    // Every city of the instance must be visited exactly once
+    if tour.len() != dimension {
+        return parse_error(format!(
+            "Expected a tour of {} cities, found {}",
+            dimension,
+            tour.len()
+        ));
+    }
+    let mut visited = vec![false; dimension];
+    for &city in &tour {
+        if city >= dimension || visited[city] {
+            return parse_error(format!(
+                "City {} is not in the instance or repeated",
+                city + 1
+            ));
+        }
+        visited[city] = true;
+    }
+
+    Ok(tour)
+}
+
+/// Load a TSPLIB instance from a .tsp file
+pub fn load_instance(path: &Path) -> Result<TspInstance, TspLibError> {
+    parse_instance(&fs::read_to_string(path)?)
+}
+
+/// Load a tour from a TSPLIB .tour file for an instance of the given dimension
+pub fn load_tour(path: &Path, dimension: usize) -> Result<Vec<usize>, TspLibError> {
+    parse_tour(&fs::read_to_string(path)?, dimension)
+}
+
+/// Total length of a closed tour through cities of the square distance matrix
+pub fn tour_length(tour: &[usize], distances: &[f64]) -> f64 {
+    let dimension = (distances.len() as f64).sqrt().round() as usize;
+    let n = tour.len();
+
+    (0..n)
+        .map(|i| distances[tour[i] * dimension + tour[(i + 1) % n]])
+        .sum()
+}
+
+#[test]
+fn test_parse_euc_2d() {
+    let instance = parse_instance(
+        "NAME : square
+TYPE : TSP
+DIMENSION : 4
+EDGE_WEIGHT_TYPE : EUC_2D
+NODE_COORD_SECTION
+1 0 0
+2 3 0
+3 3 4
+4 0 4
+EOF",
+    )
+    .unwrap();
+
+    assert_eq!(instance.name, "square");
+    assert_eq!(instance.dimension, 4);
+    assert_eq!(instance.distances[1], 3.0);
+    assert_eq!(instance.distances[2], 5.0);
+    assert_eq!(tour_length(&[0, 1, 2, 3], &instance.distances), 14.0);
+}
+
+#[test]
+fn test_parse_explicit_formats() {
+    let full = parse_instance(
+        "DIMENSION: 3
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: FULL_MATRIX
+EDGE_WEIGHT_SECTION
+0 1 2
+1 0 3
+2 3 0
+EOF",
+    )
+    .unwrap();
+    let upper = parse_instance(
+        "DIMENSION: 3
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_ROW
+EDGE_WEIGHT_SECTION
+1 2
+3
+EOF",
+    )
+    .unwrap();
+    let lower = parse_instance(
+        "DIMENSION: 3
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: LOWER_DIAG_ROW
+EDGE_WEIGHT_SECTION
+0 1 0 2
+3 0
+EOF",
+    )
+    .unwrap();
+
+    assert_eq!(full.distances, upper.distances);
+    assert_eq!(full.distances, lower.distances);
+
+    let asymmetric = parse_instance(
+        "DIMENSION: 2
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: FULL_MATRIX
+EDGE_WEIGHT_SECTION
+0 1
+2 0
+EOF",
+    );
+    assert!(asymmetric.is_err());
+}
+
+#[test]
+fn test_parse_tour() {
+    let tour = |section: &str| {
+        parse_tour(
+            &format!(
+                "NAME : square.opt.tour\nTYPE : TOUR\nTOUR_SECTION\n{}\n-1\nEOF\n",
+                section
+            ),
+            4,
+        )
+    };
+
+    assert_eq!(tour("1\n3\n2\n4").unwrap(), vec![0, 2, 1, 3]);
+    assert!(tour("1\n3\n2").is_err());
+    assert!(tour("1\n3\n3\n4").is_err());
+    assert!(tour("1\n3\n2\n5").is_err());
+}